@@ -0,0 +1,417 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zip::{result::ZipError, write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    download::is_safe_file_name,
+    extensions::{
+        package_config::PACKAGE_FILE_NAME, package_metadata::PackageMetadataExtensions,
+        shared_package_config::SHARED_PACKAGE_FILE_NAME,
+    },
+    fetch::get_file_url,
+    integrity::{digest_eq, sha256_bytes},
+    models::{artifact::ArtifactKind, dependency::SharedPackageConfig, package::PackageMetadata},
+    registry::{local::LocalRegistry, Registry, RegistryError},
+};
+
+pub const BUNDLE_EXTENSION: &str = "qpkg";
+pub const CHECKSUMS_FILE_NAME: &str = "checksums.json";
+pub const BUNDLE_LIBS_DIR_NAME: &str = "libs";
+/// The name a registered bundle is stored under in the local registry, so it can be restored as a zip source
+pub const REGISTERED_BUNDLE_FILE_NAME: &str = "source.zip";
+
+#[derive(Debug, Error)]
+pub enum BundleError {
+    #[error("{0:?} can't be bundled")]
+    UnsupportedArtifact(ArtifactKind),
+
+    #[error("bundle is missing {0}")]
+    MissingFile(String),
+
+    #[error("bundle contains {0} which is not in its checksums")]
+    UnlistedFile(String),
+
+    #[error("bundle contains the unsafe path {0}")]
+    InvalidPath(String),
+
+    #[error("{path} in the bundle is corrupted, expected sha256 {expected} but found {actual}")]
+    Corrupted {
+        path: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error(transparent)]
+    Registry(#[from] RegistryError),
+
+    #[error(transparent)]
+    Zip(#[from] ZipError),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// checksums.json of a bundle, the SHA-256 digest of every other file in it by path
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleChecksums {
+    pub files: BTreeMap<String, String>,
+}
+
+/// A package distributed as a single `.qpkg` file, a zip archive laid out as
+///
+/// - `qpm.json` and `qpm.shared.json` of the package
+/// - `{shared_dir}/...`, the headers of the package
+/// - `libs/...`, the prebuilt binaries, named like restored dependencies
+/// - `checksums.json`, see [`BundleChecksums`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackageBundle {
+    pub shared: SharedPackageConfig,
+    pub checksums: BundleChecksums,
+    /// Every file in the bundle by path, except checksums.json
+    pub files: BTreeMap<String, Vec<u8>>,
+}
+
+/// The path of a binary in a bundle.
+/// Fails with [`BundleError::InvalidPath`] if the name of the binary is not a single path segment.
+pub fn get_bundled_binary_path(
+    info: &PackageMetadata,
+    kind: ArtifactKind,
+) -> Result<String, BundleError> {
+    let name = match kind {
+        ArtifactKind::So => info.get_so_name2(),
+        ArtifactKind::DebugSo => format!("debug_{}", info.get_so_name2().to_string_lossy()).into(),
+        ArtifactKind::Static => info.get_static_name2(),
+        ArtifactKind::Mod => format!("{}.qmod", info.id).into(),
+        ArtifactKind::Source => return Err(BundleError::UnsupportedArtifact(kind)),
+    };
+    if !is_safe_file_name(&name) {
+        return Err(BundleError::InvalidPath(
+            name.to_string_lossy().into_owned(),
+        ));
+    }
+
+    Ok(format!("{BUNDLE_LIBS_DIR_NAME}/{}", name.to_string_lossy()))
+}
+
+impl PackageBundle {
+    /// Bundles a package, taking its headers from `shared_dir` in `package_dir` and the given prebuilt binaries
+    pub fn create(
+        shared: &SharedPackageConfig,
+        package_dir: &Path,
+        binaries: &BTreeMap<ArtifactKind, PathBuf>,
+    ) -> Result<Self, BundleError> {
+        let mut files = BTreeMap::new();
+        files.insert(
+            PACKAGE_FILE_NAME.to_string(),
+            serde_json::to_vec_pretty(&shared.config)?,
+        );
+        files.insert(
+            SHARED_PACKAGE_FILE_NAME.to_string(),
+            serde_json::to_vec_pretty(shared)?,
+        );
+
+        let shared_dir = &shared.config.shared_dir;
+        collect_files(&package_dir.join(shared_dir), shared_dir, &mut files)?;
+
+        for (kind, path) in binaries {
+            let bundled_path = get_bundled_binary_path(&shared.config.info, *kind)?;
+            files.insert(bundled_path, fs::read(path)?);
+        }
+
+        let checksums = BundleChecksums {
+            files: files
+                .iter()
+                .map(|(path, data)| (path.clone(), sha256_bytes(data)))
+                .collect(),
+        };
+
+        Ok(Self {
+            shared: shared.clone(),
+            checksums,
+            files,
+        })
+    }
+
+    /// Writes the bundle to a `.qpkg` file
+    pub fn write(&self, path: &Path) -> Result<(), BundleError> {
+        let mut zip = ZipWriter::new(BufWriter::new(File::create(path)?));
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        zip.start_file(CHECKSUMS_FILE_NAME, options)?;
+        zip.write_all(&serde_json::to_vec_pretty(&self.checksums)?)?;
+        for (name, data) in &self.files {
+            zip.start_file(name, options)?;
+            zip.write_all(data)?;
+        }
+
+        zip.finish()?.flush()?;
+        Ok(())
+    }
+
+    /// Reads a `.qpkg` file, checking every file against the checksums
+    pub fn read(path: &Path) -> Result<Self, BundleError> {
+        let mut zip = ZipArchive::new(BufReader::new(File::open(path)?))?;
+
+        let mut checksums = None;
+        let mut files = BTreeMap::new();
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            if file.is_dir() {
+                continue;
+            }
+            if file.enclosed_name().is_none() {
+                return Err(BundleError::InvalidPath(file.name().to_string()));
+            }
+
+            let name = file.name().to_string();
+            let mut data = vec![];
+            file.read_to_end(&mut data)?;
+
+            if name == CHECKSUMS_FILE_NAME {
+                checksums = Some(serde_json::from_slice::<BundleChecksums>(&data)?);
+            } else {
+                files.insert(name, data);
+            }
+        }
+
+        let checksums =
+            checksums.ok_or_else(|| BundleError::MissingFile(CHECKSUMS_FILE_NAME.to_string()))?;
+        for (name, data) in &files {
+            let expected = checksums
+                .files
+                .get(name)
+                .ok_or_else(|| BundleError::UnlistedFile(name.clone()))?;
+            let actual = sha256_bytes(data);
+            if !digest_eq(expected, &actual) {
+                return Err(BundleError::Corrupted {
+                    path: name.clone(),
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+        if let Some(name) = checksums.files.keys().find(|n| !files.contains_key(*n)) {
+            return Err(BundleError::MissingFile(name.clone()));
+        }
+
+        let shared = files
+            .get(SHARED_PACKAGE_FILE_NAME)
+            .ok_or_else(|| BundleError::MissingFile(SHARED_PACKAGE_FILE_NAME.to_string()))?;
+        let shared = serde_json::from_slice(shared)?;
+
+        Ok(Self {
+            shared,
+            checksums,
+            files,
+        })
+    }
+
+    /// The prebuilt binaries in the bundle by kind
+    pub fn get_binaries(&self) -> BTreeMap<ArtifactKind, &Vec<u8>> {
+        [
+            ArtifactKind::So,
+            ArtifactKind::DebugSo,
+            ArtifactKind::Static,
+            ArtifactKind::Mod,
+        ]
+        .into_iter()
+        .filter_map(|kind| {
+            let path = get_bundled_binary_path(&self.shared.config.info, kind).ok()?;
+            Some((kind, self.files.get(&path)?))
+        })
+        .collect()
+    }
+}
+
+/// Publishes a bundle to a local registry, so it can be restored without network access.
+///
+/// The bundle is stored next to the published config as `source.zip` and its binaries in `libs/`.
+/// The links and url of the published config are rewritten to `file://` urls of these files.
+pub fn register_bundle(
+    bundle_path: &Path,
+    registry: &LocalRegistry,
+) -> Result<SharedPackageConfig, BundleError> {
+    let bundle = PackageBundle::read(bundle_path)?;
+    let mut shared = bundle.shared.clone();
+    let info = &shared.config.info;
+
+    // the names of the binaries come from the untrusted bundle and are written into the registry
+    for kind in [
+        ArtifactKind::So,
+        ArtifactKind::DebugSo,
+        ArtifactKind::Static,
+        ArtifactKind::Mod,
+    ] {
+        get_bundled_binary_path(info, kind)?;
+    }
+
+    // check before writing any file, so the files of a published version are not replaced
+    match registry.get_shared_package(&info.id, &info.version) {
+        Ok(_) => {
+            return Err(
+                RegistryError::AlreadyPublished(info.id.clone(), info.version.clone()).into(),
+            )
+        }
+        Err(RegistryError::VersionNotFound(..) | RegistryError::PackageNotFound(_)) => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let version_dir = registry.get_version_dir(&info.id, &info.version)?;
+    fs::create_dir_all(version_dir.join(BUNDLE_LIBS_DIR_NAME))?;
+    let version_dir = fs::canonicalize(version_dir)?;
+
+    let source_path = version_dir.join(REGISTERED_BUNDLE_FILE_NAME);
+    fs::copy(bundle_path, &source_path)?;
+
+    let mut links = vec![];
+    for (kind, data) in bundle.get_binaries() {
+        let path = version_dir.join(get_bundled_binary_path(info, kind)?);
+        fs::write(&path, data)?;
+        links.push((kind, get_file_url(&path), sha256_bytes(data)));
+    }
+
+    let info = &mut shared.config.info;
+    info.url = Some(get_file_url(&source_path));
+    let metadata = &mut info.additional_data;
+    // the bundle root is the package directory
+    metadata.sub_folder = None;
+    metadata.branch_name = None;
+    for (kind, url, sha256) in links {
+        let (link, digest) = match kind {
+            ArtifactKind::So => (&mut metadata.so_link, &mut metadata.so_sha256),
+            ArtifactKind::DebugSo => (&mut metadata.debug_so_link, &mut metadata.debug_so_sha256),
+            ArtifactKind::Static => (&mut metadata.static_link, &mut metadata.static_sha256),
+            ArtifactKind::Mod => (&mut metadata.mod_link, &mut metadata.mod_sha256),
+            ArtifactKind::Source => continue,
+        };
+        *link = Some(url);
+        *digest = Some(sha256);
+    }
+
+    registry.publish(&shared)?;
+    Ok(shared)
+}
+
+/// Adds every file in `dir` to `files`, keyed by its path under `prefix` with `/` separators
+fn collect_files(
+    dir: &Path,
+    prefix: &Path,
+    files: &mut BTreeMap<String, Vec<u8>>,
+) -> Result<(), BundleError> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let prefix = prefix.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), &prefix, files)?;
+            continue;
+        }
+
+        let name = prefix
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.insert(name, fs::read(entry.path())?);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::Version;
+
+    use super::*;
+    use crate::models::package::PackageConfig;
+
+    /// A package with one header and a prebuilt so, bundled and written to `{dir}/package.qpkg`
+    fn write_bundle(dir: &Path, so_name: Option<&str>) -> PathBuf {
+        let package_dir = dir.join("package");
+        fs::create_dir_all(package_dir.join("shared")).unwrap();
+        fs::write(package_dir.join("shared/header.hpp"), "#pragma once").unwrap();
+        fs::write(dir.join("lib.so"), "binary").unwrap();
+
+        let mut config = PackageConfig {
+            shared_dir: "shared".into(),
+            ..Default::default()
+        };
+        config.info.id = "package".to_string();
+        config.info.version = Version::new(1, 0, 0);
+        config.info.additional_data.override_so_name = so_name.map(str::to_string);
+        let shared = SharedPackageConfig {
+            config,
+            restored_dependencies: vec![],
+        };
+
+        let mut binaries = BTreeMap::new();
+        if so_name.is_none() {
+            binaries.insert(ArtifactKind::So, dir.join("lib.so"));
+        }
+        let bundle = PackageBundle::create(&shared, &package_dir, &binaries).unwrap();
+        let path = dir.join(format!("package.{BUNDLE_EXTENSION}"));
+        bundle.write(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn registers_bundle() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let bundle_path = write_bundle(dir, None);
+        let registry = LocalRegistry::new(dir.join("registry"));
+
+        let shared = register_bundle(&bundle_path, &registry).unwrap();
+        let metadata = &shared.config.info.additional_data;
+        assert_eq!(
+            metadata.so_sha256.as_deref(),
+            Some(sha256_bytes(b"binary").as_str())
+        );
+        assert!(metadata
+            .so_link
+            .as_ref()
+            .is_some_and(|link| link.starts_with("file://")));
+
+        let version_dir = registry
+            .get_version_dir("package", &Version::new(1, 0, 0))
+            .unwrap();
+        let so_path = version_dir
+            .join(get_bundled_binary_path(&shared.config.info, ArtifactKind::So).unwrap());
+        assert_eq!(fs::read(so_path).unwrap(), b"binary");
+        assert!(version_dir.join(REGISTERED_BUNDLE_FILE_NAME).is_file());
+        assert_eq!(
+            registry
+                .get_shared_package("package", &Version::new(1, 0, 0))
+                .unwrap(),
+            shared
+        );
+
+        let result = register_bundle(&bundle_path, &registry);
+        assert!(matches!(
+            result,
+            Err(BundleError::Registry(RegistryError::AlreadyPublished(..)))
+        ));
+    }
+
+    #[test]
+    fn rejects_unsafe_binary_names() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let bundle_path = write_bundle(dir, Some("../escaped.so"));
+        let registry = LocalRegistry::new(dir.join("registry"));
+
+        let result = register_bundle(&bundle_path, &registry);
+        assert!(matches!(result, Err(BundleError::InvalidPath(name)) if name == "../escaped.so"));
+        assert!(!registry.root.exists());
+    }
+}
@@ -0,0 +1,456 @@
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    integrity::{sha256_bytes, sha256_file},
+    models::{artifact::ArtifactKind, dependency::SharedPackageConfig},
+};
+
+pub const INDEX_FILE_NAME: &str = "index.json";
+pub const OBJECTS_DIR_NAME: &str = "objects";
+
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error(
+        "cached artifact {path:?} is corrupted, expected sha256 {expected} but found {actual}"
+    )]
+    Corrupted {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("{0:?} is not a lowercase hex sha256 digest")]
+    InvalidDigest(String),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// An artifact stored in the cache
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntry {
+    pub id: String,
+    pub version: Version,
+    pub kind: ArtifactKind,
+    /// The lowercase hex SHA-256 digest of the artifact, which is also its location in the store
+    pub sha256: String,
+    pub size: u64,
+    /// The url the artifact was downloaded from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// {cache}/index.json
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheIndex {
+    pub entries: Vec<CacheEntry>,
+}
+
+/// What [`PackageCache::gc`] removed
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GcStats {
+    pub removed_entries: usize,
+    pub removed_objects: usize,
+    pub freed_bytes: u64,
+}
+
+/// A content addressed store of downloaded artifacts.
+/// Artifacts are stored in `{root}/objects/{sha256[..2]}/{sha256}` and indexed by id, version and kind
+/// in `{root}/index.json`, so identical artifacts are only stored once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackageCache {
+    pub root: PathBuf,
+    pub index: CacheIndex,
+}
+
+impl PackageCache {
+    /// Opens the cache at `root`, starting with an empty index if there is none yet
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self, CacheError> {
+        let root = root.into();
+        let index = match File::open(root.join(INDEX_FILE_NAME)) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => CacheIndex::default(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self { root, index })
+    }
+
+    /// Writes the index to disk
+    pub fn save(&self) -> Result<(), CacheError> {
+        fs::create_dir_all(&self.root)?;
+
+        // write to a temporary file first so an interrupted save can't corrupt the index
+        let path = self.root.join(INDEX_FILE_NAME);
+        let tmp_path = path.with_extension("json.tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer_pretty(&mut writer, &self.index)?;
+        writer.into_inner().map_err(|e| e.into_error())?;
+        fs::rename(tmp_path, path)?;
+
+        Ok(())
+    }
+
+    /// The path an artifact with the given digest is stored at.
+    /// The digest must be 64 lowercase hex characters, so it can't point outside of the store.
+    pub fn get_object_path(&self, sha256: &str) -> Result<PathBuf, CacheError> {
+        if !is_sha256(sha256) {
+            return Err(CacheError::InvalidDigest(sha256.to_string()));
+        }
+
+        Ok(self
+            .root
+            .join(OBJECTS_DIR_NAME)
+            .join(&sha256[..2])
+            .join(sha256))
+    }
+
+    /// Looks up a cached artifact, ignoring entries whose object is missing or whose digest is invalid
+    pub fn get(
+        &self,
+        id: &str,
+        version: &Version,
+        kind: ArtifactKind,
+    ) -> Option<(&CacheEntry, PathBuf)> {
+        self.index
+            .entries
+            .iter()
+            .find(|e| e.id == id && &e.version == version && e.kind == kind)
+            .and_then(|e| Some((e, self.get_object_path(&e.sha256).ok()?)))
+            .filter(|(_, path)| path.is_file())
+    }
+
+    /// Looks up a cached artifact by its digest
+    pub fn get_by_hash(&self, sha256: &str) -> Option<PathBuf> {
+        self.get_object_path(sha256)
+            .ok()
+            .filter(|path| path.is_file())
+    }
+
+    /// Checks that the object of an entry still matches its digest
+    pub fn verify(&self, entry: &CacheEntry) -> Result<(), CacheError> {
+        let path = self.get_object_path(&entry.sha256)?;
+        let actual = sha256_file(&path)?;
+        if actual != entry.sha256 {
+            return Err(CacheError::Corrupted {
+                path,
+                expected: entry.sha256.clone(),
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Stores an artifact, replacing the entry previously stored for the same id, version and kind.
+    /// The index is not saved.
+    pub fn insert(
+        &mut self,
+        id: &str,
+        version: &Version,
+        kind: ArtifactKind,
+        url: Option<&str>,
+        data: &[u8],
+    ) -> Result<&CacheEntry, CacheError> {
+        let sha256 = sha256_bytes(data);
+        self.store_object(&sha256, |tmp_path| fs::write(tmp_path, data))?;
+
+        let entry = CacheEntry {
+            id: id.to_string(),
+            version: version.clone(),
+            kind,
+            sha256,
+            size: data.len() as u64,
+            url: url.map(str::to_string),
+        };
+        Ok(self.insert_entry(entry))
+    }
+
+    /// Stores an artifact from a file, see [`PackageCache::insert`]
+    pub fn insert_file(
+        &mut self,
+        id: &str,
+        version: &Version,
+        kind: ArtifactKind,
+        url: Option<&str>,
+        file: impl AsRef<Path>,
+    ) -> Result<&CacheEntry, CacheError> {
+        let file = file.as_ref();
+        let sha256 = sha256_file(file)?;
+        let path = self.store_object(&sha256, |tmp_path| fs::copy(file, tmp_path).map(|_| ()))?;
+
+        let entry = CacheEntry {
+            id: id.to_string(),
+            version: version.clone(),
+            kind,
+            sha256,
+            size: fs::metadata(&path)?.len(),
+            url: url.map(str::to_string),
+        };
+        Ok(self.insert_entry(entry))
+    }
+
+    /// Writes the object of a digest through a temporary file,
+    /// unless it is stored already and still matches the digest. A corrupted object is replaced.
+    fn store_object(
+        &self,
+        sha256: &str,
+        write: impl FnOnce(&Path) -> io::Result<()>,
+    ) -> Result<PathBuf, CacheError> {
+        let path = self.get_object_path(sha256)?;
+        if path.is_file() && sha256_file(&path)? == sha256 {
+            return Ok(path);
+        }
+
+        fs::create_dir_all(path.parent().unwrap())?;
+        let tmp_path = path.with_extension("tmp");
+        write(&tmp_path)?;
+        fs::rename(tmp_path, &path)?;
+        Ok(path)
+    }
+
+    fn insert_entry(&mut self, entry: CacheEntry) -> &CacheEntry {
+        let entries = &mut self.index.entries;
+        entries
+            .retain(|e| !(e.id == entry.id && e.version == entry.version && e.kind == entry.kind));
+        entries.push(entry);
+        entries.last().unwrap()
+    }
+
+    /// The size of all stored objects, counting artifacts shared by multiple entries once
+    pub fn get_total_size(&self) -> u64 {
+        let mut seen = BTreeSet::new();
+        self.index
+            .entries
+            .iter()
+            .filter(|e| seen.insert(&e.sha256))
+            .map(|e| e.size)
+            .sum()
+    }
+
+    /// Removes every entry whose package is not the package or a restored dependency of one of `configs`,
+    /// then deletes the objects no entry refers to anymore. Temporary files of objects being stored are kept.
+    /// The index is saved afterwards.
+    pub fn gc<'a>(
+        &mut self,
+        configs: impl IntoIterator<Item = &'a SharedPackageConfig>,
+    ) -> Result<GcStats, CacheError> {
+        let mut referenced: BTreeSet<(&str, &Version)> = BTreeSet::new();
+        for config in configs {
+            let info = &config.config.info;
+            referenced.insert((&info.id, &info.version));
+            referenced.extend(
+                config
+                    .restored_dependencies
+                    .iter()
+                    .map(|d| (d.dependency.id.as_str(), &d.version)),
+            );
+        }
+
+        let mut stats = GcStats::default();
+        let before = self.index.entries.len();
+        self.index
+            .entries
+            .retain(|e| referenced.contains(&(e.id.as_str(), &e.version)));
+        stats.removed_entries = before - self.index.entries.len();
+
+        let live: BTreeSet<&str> = self
+            .index
+            .entries
+            .iter()
+            .map(|e| e.sha256.as_str())
+            .collect();
+
+        let objects_dir = self.root.join(OBJECTS_DIR_NAME);
+        if objects_dir.is_dir() {
+            for prefix_dir in fs::read_dir(&objects_dir)? {
+                let prefix_dir = prefix_dir?.path();
+                if !prefix_dir.is_dir() {
+                    continue;
+                }
+
+                for object in fs::read_dir(&prefix_dir)? {
+                    let object = object?;
+                    let name = object.file_name();
+                    let name = name.to_str();
+                    // temporary files are still being written by a concurrent store
+                    let is_tmp = name.is_some_and(|n| n.ends_with(".tmp"));
+                    if is_tmp || name.is_some_and(|n| live.contains(n)) {
+                        continue;
+                    }
+
+                    stats.freed_bytes += object.metadata()?.len();
+                    stats.removed_objects += 1;
+                    fs::remove_file(object.path())?;
+                }
+
+                // only succeeds once the directory is empty
+                let _ = fs::remove_dir(&prefix_dir);
+            }
+        }
+
+        self.save()?;
+        Ok(stats)
+    }
+}
+
+/// Whether a value is a lowercase hex SHA-256 digest, as the objects of the cache are named
+pub fn is_sha256(value: &str) -> bool {
+    value.len() == 64
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::dependency::{Dependency, SharedDependency};
+
+    fn get_cache() -> (tempfile::TempDir, PackageCache) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = PackageCache::open(temp_dir.path()).unwrap();
+        (temp_dir, cache)
+    }
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    #[allow(deprecated)]
+    fn shared(id: &str, dependencies: &[(&str, &str)]) -> SharedPackageConfig {
+        let mut shared = SharedPackageConfig {
+            config: Default::default(),
+            restored_dependencies: vec![],
+        };
+        shared.config.info.id = id.to_string();
+        shared.config.info.version = version("1.0.0");
+        for (id, version) in dependencies {
+            shared.restored_dependencies.push(SharedDependency {
+                dependency: Dependency {
+                    id: id.to_string(),
+                    version_range: semver::VersionReq::STAR,
+                    additional_data: Default::default(),
+                },
+                version: Version::parse(version).unwrap(),
+                checksums: Default::default(),
+                publisher_key: None,
+                source: None,
+                required_by: vec![],
+                declared_id: None,
+                applied_override: None,
+            });
+        }
+        shared
+    }
+
+    /// Stores the shared object of a package
+    fn store(cache: &mut PackageCache, id: &str, version: &str, data: &[u8]) -> CacheEntry {
+        let version = Version::parse(version).unwrap();
+        cache
+            .insert(id, &version, ArtifactKind::So, None, data)
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn stores_identical_artifacts_once() {
+        let (_dir, mut cache) = get_cache();
+        let so = ArtifactKind::So;
+        store(&mut cache, "a", "1.0.0", b"lib");
+        let shared_entry = store(&mut cache, "b", "1.0.0", b"lib");
+        // replaces the previous entry of the same id, version and kind
+        let entry = store(&mut cache, "a", "1.0.0", b"new");
+
+        assert_eq!(cache.index.entries.len(), 2);
+        assert_eq!(cache.get_total_size(), 6);
+        let (found, path) = cache.get("a", &version("1.0.0"), so).unwrap();
+        assert_eq!(found, &entry);
+        assert_eq!(fs::read(path).unwrap(), b"new");
+        assert_eq!(
+            cache.get_by_hash(&shared_entry.sha256),
+            Some(cache.get_object_path(&sha256_bytes(b"lib")).unwrap())
+        );
+        assert!(cache.get("a", &version("2.0.0"), so).is_none());
+
+        cache.save().unwrap();
+        let reopened = PackageCache::open(&cache.root).unwrap();
+        assert_eq!(reopened.index, cache.index);
+    }
+
+    #[test]
+    fn rejects_invalid_digests_and_corrupted_objects() {
+        let (_dir, mut cache) = get_cache();
+        for digest in ["../../index.json", &"A".repeat(64), &"a".repeat(63)] {
+            assert!(matches!(
+                cache.get_object_path(digest),
+                Err(CacheError::InvalidDigest(_))
+            ));
+            assert!(cache.get_by_hash(digest).is_none());
+        }
+
+        let entry = store(&mut cache, "a", "1.0.0", b"lib");
+        cache.verify(&entry).unwrap();
+
+        let path = cache.get_object_path(&entry.sha256).unwrap();
+        fs::write(&path, b"tampered").unwrap();
+        assert!(matches!(
+            cache.verify(&entry),
+            Err(CacheError::Corrupted { actual, .. }) if actual == sha256_bytes(b"tampered")
+        ));
+
+        // storing the artifact again replaces the corrupted object
+        store(&mut cache, "a", "1.0.0", b"lib");
+        cache.verify(&entry).unwrap();
+    }
+
+    #[test]
+    fn collects_unreferenced_artifacts() {
+        let (_dir, mut cache) = get_cache();
+        let so = ArtifactKind::So;
+        store(&mut cache, "a", "1.0.0", b"a");
+        store(&mut cache, "b", "1.0.0", b"b");
+        store(&mut cache, "b", "2.0.0", b"b2");
+        // shares its object with a referenced entry
+        store(&mut cache, "c", "1.0.0", b"a");
+
+        // a store in progress
+        let tmp_path = cache
+            .get_object_path(&sha256_bytes(b"pending"))
+            .unwrap()
+            .with_extension("tmp");
+        fs::create_dir_all(tmp_path.parent().unwrap()).unwrap();
+        fs::write(&tmp_path, b"pending").unwrap();
+
+        let stats = cache.gc([&shared("a", &[("b", "1.0.0")])]).unwrap();
+        assert_eq!(
+            stats,
+            GcStats {
+                removed_entries: 2,
+                removed_objects: 1,
+                freed_bytes: 2,
+            }
+        );
+        assert!(cache.get("a", &version("1.0.0"), so).is_some());
+        assert!(cache.get("b", &version("1.0.0"), so).is_some());
+        assert!(cache.get_by_hash(&sha256_bytes(b"b2")).is_none());
+        assert!(tmp_path.is_file());
+
+        let reopened = PackageCache::open(&cache.root).unwrap();
+        assert_eq!(reopened.index.entries.len(), 2);
+    }
+}
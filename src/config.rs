@@ -0,0 +1,493 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    fmt::Display,
+    fs::File,
+    io::{self, BufReader, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+    models::{
+        auth::AuthConfig,
+        mirror::MirrorConfig,
+        user_config::{RegistryConfig, ResolutionStrategy, TokenReference, UserConfig},
+    },
+    registry::http::DEFAULT_REGISTRY_URL,
+};
+
+pub const USER_CONFIG_FILE_NAME: &str = "qpm.settings.json";
+
+pub const CACHE_DIR_ENV: &str = "QPM_CACHE_DIR";
+pub const REGISTRY_URL_ENV: &str = "QPM_REGISTRY_URL";
+/// The name of an environment variable holding the token of the default registry
+pub const REGISTRY_TOKEN_ENV_ENV: &str = "QPM_REGISTRY_TOKEN_ENV";
+/// Separated like `PATH`
+pub const NDK_PATHS_ENV: &str = "QPM_NDK_PATHS";
+pub const RESOLUTION_STRATEGY_ENV: &str = "QPM_RESOLUTION_STRATEGY";
+
+/// Settings that receive or reference secrets, which project settings can't set
+pub const USER_ONLY_SETTINGS: &[&str] = &["registryUrl", "registryToken", "registries", "auth"];
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("{name} has the invalid value {value:?}: {reason}")]
+    InvalidEnv {
+        name: String,
+        value: String,
+        reason: String,
+    },
+
+    #[error("{name} can only be set in the user settings, but is set in {path:?}")]
+    UserOnlySetting { name: String, path: PathBuf },
+
+    #[error("failed to read {path:?}: {source}")]
+    Json {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Where an effective setting came from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    /// The settings file of the user
+    User(PathBuf),
+    /// The settings file of the project
+    Project(PathBuf),
+    /// A `QPM_*` environment variable
+    Env(String),
+}
+
+impl Display for ConfigSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::User(path) => write!(f, "user settings {}", path.display()),
+            ConfigSource::Project(path) => write!(f, "project settings {}", path.display()),
+            ConfigSource::Env(name) => write!(f, "environment variable {name}"),
+        }
+    }
+}
+
+/// An effective setting and where it came from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sourced<T> {
+    pub value: T,
+    pub source: ConfigSource,
+}
+
+impl<T> Sourced<T> {
+    pub fn new(value: T, source: ConfigSource) -> Self {
+        Self { value, source }
+    }
+}
+
+/// The settings in effect after layering, see [`ConfigLayers::resolve`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EffectiveConfig {
+    pub cache_dir: Sourced<PathBuf>,
+    pub registry_url: Sourced<String>,
+    pub registry_token: Option<Sourced<TokenReference>>,
+    pub registries: BTreeMap<String, Sourced<RegistryConfig>>,
+    pub ndk_paths: Sourced<Vec<PathBuf>>,
+    pub mirrors: Sourced<MirrorConfig>,
+    pub auth: Sourced<AuthConfig>,
+    pub resolution_strategy: Sourced<ResolutionStrategy>,
+}
+
+impl EffectiveConfig {
+    /// Where every setting came from by its name in qpm.settings.json, for reporting
+    pub fn get_sources(&self) -> Vec<(String, &ConfigSource)> {
+        let mut sources = vec![
+            ("cacheDir".to_string(), &self.cache_dir.source),
+            ("registryUrl".to_string(), &self.registry_url.source),
+        ];
+        if let Some(token) = &self.registry_token {
+            sources.push(("registryToken".to_string(), &token.source));
+        }
+        for (name, registry) in &self.registries {
+            sources.push((format!("registries.{name}"), &registry.source));
+        }
+        sources.extend([
+            ("ndkPaths".to_string(), &self.ndk_paths.source),
+            ("mirrors".to_string(), &self.mirrors.source),
+            ("auth".to_string(), &self.auth.source),
+            (
+                "resolutionStrategy".to_string(),
+                &self.resolution_strategy.source,
+            ),
+        ]);
+        sources
+    }
+}
+
+/// The settings of every layer, from lowest to highest precedence:
+/// defaults, the user settings, the project settings and `QPM_*` environment variables.
+///
+/// Project settings are checked into repositories that may not be trusted, so settings deciding where
+/// tokens are sent and how they are read can only be set by the user, see [`USER_ONLY_SETTINGS`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigLayers {
+    pub user: Option<(PathBuf, UserConfig)>,
+    pub project: Option<(PathBuf, UserConfig)>,
+    /// `QPM_*` environment variables
+    pub env: BTreeMap<String, String>,
+}
+
+impl ConfigLayers {
+    /// Loads the user settings from [`get_user_config_path`], the project settings from `project_dir`
+    /// and the environment of the process. Missing settings files are skipped.
+    pub fn load(project_dir: Option<&Path>) -> Result<Self, ConfigError> {
+        let user = get_user_config_path()
+            .map(|path| read_user_config(&path).map(|config| config.map(|c| (path, c))))
+            .transpose()?
+            .flatten();
+
+        let project = project_dir
+            .map(|dir| {
+                let path = dir.join(USER_CONFIG_FILE_NAME);
+                read_user_config(&path).map(|config| config.map(|c| (path, c)))
+            })
+            .transpose()?
+            .flatten();
+
+        let env = env::vars().filter(|(k, _)| k.starts_with("QPM_")).collect();
+
+        Ok(Self { user, project, env })
+    }
+
+    /// Layers the settings, each setting is taken from the highest layer that sets it.
+    /// Fails with [`ConfigError::UserOnlySetting`] if the project settings set a user only setting.
+    pub fn resolve(&self) -> Result<EffectiveConfig, ConfigError> {
+        self.check_project()?;
+
+        let cache_dir = self
+            .get_env(CACHE_DIR_ENV, |v| Ok(PathBuf::from(v)))?
+            .or_else(|| self.get_file(|c| c.cache_dir.clone()))
+            .unwrap_or_else(|| Sourced::new(get_default_cache_dir(), ConfigSource::Default));
+
+        let registry_url = self
+            .get_env(REGISTRY_URL_ENV, |v| Ok(v.to_string()))?
+            .or_else(|| self.get_user_file(|c| c.registry_url.clone()))
+            .unwrap_or_else(|| {
+                Sourced::new(DEFAULT_REGISTRY_URL.to_string(), ConfigSource::Default)
+            });
+
+        let registry_token = self
+            .get_env(REGISTRY_TOKEN_ENV_ENV, |v| {
+                Ok(TokenReference::Env {
+                    name: v.to_string(),
+                })
+            })?
+            .or_else(|| self.get_user_file(|c| c.registry_token.clone()));
+
+        let registries = self
+            .get_user_file(|c| c.registries.clone())
+            .map(|registries| {
+                let source = registries.source;
+                registries
+                    .value
+                    .into_iter()
+                    .map(|(name, registry)| (name, Sourced::new(registry, source.clone())))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let ndk_paths = self
+            .get_env(NDK_PATHS_ENV, |v| Ok(env::split_paths(v).collect()))?
+            .or_else(|| self.get_file(|c| c.ndk_paths.clone()))
+            .unwrap_or_else(|| Sourced::new(vec![], ConfigSource::Default));
+
+        let mirrors = self
+            .get_file(|c| c.mirrors.clone())
+            .unwrap_or_else(|| Sourced::new(MirrorConfig::default(), ConfigSource::Default));
+
+        let auth = self
+            .get_user_file(|c| c.auth.clone())
+            .unwrap_or_else(|| Sourced::new(AuthConfig::default(), ConfigSource::Default));
+
+        let resolution_strategy = self
+            .get_env(RESOLUTION_STRATEGY_ENV, |v| {
+                serde_json::from_value(serde_json::Value::String(v.to_string()))
+                    .map_err(|e| e.to_string())
+            })?
+            .or_else(|| self.get_file(|c| c.resolution_strategy))
+            .unwrap_or_else(|| Sourced::new(ResolutionStrategy::default(), ConfigSource::Default));
+
+        Ok(EffectiveConfig {
+            cache_dir,
+            registry_url,
+            registry_token,
+            registries,
+            ndk_paths,
+            mirrors,
+            auth,
+            resolution_strategy,
+        })
+    }
+
+    /// The settings files from lowest to highest precedence
+    fn get_files(&self) -> impl Iterator<Item = (ConfigSource, &UserConfig)> {
+        let user = self
+            .user
+            .iter()
+            .map(|(path, config)| (ConfigSource::User(path.clone()), config));
+        let project = self
+            .project
+            .iter()
+            .map(|(path, config)| (ConfigSource::Project(path.clone()), config));
+        user.chain(project)
+    }
+
+    /// Rejects user only settings in the project settings
+    fn check_project(&self) -> Result<(), ConfigError> {
+        let Some((path, config)) = &self.project else {
+            return Ok(());
+        };
+
+        let value = serde_json::to_value(config).map_err(|source| ConfigError::Json {
+            path: path.clone(),
+            source,
+        })?;
+        match USER_ONLY_SETTINGS
+            .iter()
+            .find(|name| value.get(**name).is_some())
+        {
+            Some(name) => Err(ConfigError::UserOnlySetting {
+                name: name.to_string(),
+                path: path.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// A setting of the user settings, for settings that can't be set by the project
+    fn get_user_file<T>(&self, get: impl Fn(&UserConfig) -> Option<T>) -> Option<Sourced<T>> {
+        let (path, config) = self.user.as_ref()?;
+        get(config).map(|value| Sourced::new(value, ConfigSource::User(path.clone())))
+    }
+
+    fn get_file<T>(&self, get: impl Fn(&UserConfig) -> Option<T>) -> Option<Sourced<T>> {
+        self.get_files()
+            .filter_map(|(source, config)| get(config).map(|value| Sourced::new(value, source)))
+            .last()
+    }
+
+    fn get_env<T>(
+        &self,
+        name: &str,
+        parse: impl Fn(&str) -> Result<T, String>,
+    ) -> Result<Option<Sourced<T>>, ConfigError> {
+        let Some(value) = self.env.get(name).filter(|v| !v.is_empty()) else {
+            return Ok(None);
+        };
+
+        let parsed = parse(value).map_err(|reason| ConfigError::InvalidEnv {
+            name: name.to_string(),
+            value: value.clone(),
+            reason,
+        })?;
+        Ok(Some(Sourced::new(
+            parsed,
+            ConfigSource::Env(name.to_string()),
+        )))
+    }
+}
+
+/// Reads a settings file, `None` if it does not exist
+pub fn read_user_config(path: &Path) -> Result<Option<UserConfig>, ConfigError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    serde_json::from_reader(BufReader::new(file))
+        .map(Some)
+        .map_err(|source| ConfigError::Json {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// `%APPDATA%\QPM\qpm.settings.json` on Windows, `$XDG_CONFIG_HOME/qpm/qpm.settings.json` or
+/// `~/.config/qpm/qpm.settings.json` elsewhere
+pub fn get_user_config_path() -> Option<PathBuf> {
+    let dir = if cfg!(windows) {
+        env::var_os("APPDATA").map(|d| PathBuf::from(d).join("QPM"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+            .map(|d| d.join("qpm"))
+    };
+
+    dir.map(|d| d.join(USER_CONFIG_FILE_NAME))
+}
+
+/// `%LOCALAPPDATA%\QPM\cache` on Windows, `$XDG_CACHE_HOME/qpm` or `~/.cache/qpm` elsewhere.
+/// Falls back to a directory in the temporary directory.
+pub fn get_default_cache_dir() -> PathBuf {
+    let dir = if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(|d| PathBuf::from(d).join("QPM").join("cache"))
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))
+            .map(|d| d.join("qpm"))
+    };
+
+    dir.unwrap_or_else(|| env::temp_dir().join("qpm"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(user: UserConfig, project: UserConfig, env: &[(&str, &str)]) -> ConfigLayers {
+        ConfigLayers {
+            user: Some(("user.json".into(), user)),
+            project: Some(("project.json".into(), project)),
+            env: env
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn takes_each_setting_from_the_highest_layer() {
+        let user = UserConfig {
+            cache_dir: Some("user-cache".into()),
+            registry_url: Some("https://registry.test".to_string()),
+            ndk_paths: Some(vec!["user-ndk".into()]),
+            resolution_strategy: Some(ResolutionStrategy::Lowest),
+            ..Default::default()
+        };
+        let project = UserConfig {
+            cache_dir: Some("project-cache".into()),
+            ..Default::default()
+        };
+        let user_source = ConfigSource::User("user.json".into());
+        let project_source = ConfigSource::Project("project.json".into());
+
+        let config = layers(
+            user,
+            project,
+            &[(RESOLUTION_STRATEGY_ENV, "highest"), (NDK_PATHS_ENV, "")],
+        )
+        .resolve()
+        .unwrap();
+
+        assert_eq!(
+            config.cache_dir,
+            Sourced::new("project-cache".into(), project_source)
+        );
+        assert_eq!(
+            config.registry_url,
+            Sourced::new("https://registry.test".to_string(), user_source.clone())
+        );
+        assert_eq!(config.registry_token, None);
+        // empty variables are ignored
+        assert_eq!(
+            config.ndk_paths,
+            Sourced::new(vec!["user-ndk".into()], user_source)
+        );
+        assert_eq!(
+            config.resolution_strategy,
+            Sourced::new(
+                ResolutionStrategy::Highest,
+                ConfigSource::Env(RESOLUTION_STRATEGY_ENV.to_string())
+            )
+        );
+        assert_eq!(config.mirrors.source, ConfigSource::Default);
+
+        let sources: Vec<_> = config
+            .get_sources()
+            .into_iter()
+            .map(|(name, source)| format!("{name}: {source}"))
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                "cacheDir: project settings project.json",
+                "registryUrl: user settings user.json",
+                "ndkPaths: user settings user.json",
+                "mirrors: default",
+                "auth: default",
+                "resolutionStrategy: environment variable QPM_RESOLUTION_STRATEGY",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_user_only_settings_in_project_settings() {
+        let project = UserConfig {
+            registry_token: Some(TokenReference::Env {
+                name: "TOKEN".to_string(),
+            }),
+            ..Default::default()
+        };
+        assert!(matches!(
+            layers(Default::default(), project.clone(), &[]).resolve(),
+            Err(ConfigError::UserOnlySetting { name, .. }) if name == "registryToken"
+        ));
+
+        // the same settings are allowed for the user
+        let config = layers(project, Default::default(), &[(CACHE_DIR_ENV, "cache")])
+            .resolve()
+            .unwrap();
+        assert_eq!(
+            config.registry_token.unwrap().source,
+            ConfigSource::User("user.json".into())
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_environment_variables() {
+        let result = layers(
+            Default::default(),
+            Default::default(),
+            &[
+                (CACHE_DIR_ENV, "cache"),
+                (RESOLUTION_STRATEGY_ENV, "newest"),
+            ],
+        )
+        .resolve();
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidEnv { name, value, .. })
+                if name == RESOLUTION_STRATEGY_ENV && value == "newest"
+        ));
+    }
+
+    #[test]
+    fn reads_settings_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(USER_CONFIG_FILE_NAME);
+        assert_eq!(read_user_config(&path).unwrap(), None);
+
+        std::fs::write(&path, r#"{ "ndkPaths": ["ndk"] }"#).unwrap();
+        assert_eq!(
+            read_user_config(&path).unwrap(),
+            Some(UserConfig {
+                ndk_paths: Some(vec!["ndk".into()]),
+                ..Default::default()
+            })
+        );
+
+        std::fs::write(&path, r#"{ "ndkPaths": "ndk" }"#).unwrap();
+        assert!(matches!(
+            read_user_config(&path),
+            Err(ConfigError::Json { path: error_path, .. }) if error_path == path
+        ));
+    }
+}
@@ -0,0 +1,696 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt::{self, Debug},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+    credentials::redact_url,
+    extensions::{
+        additional_metadata::AdditionalPackageMetadataExtensions,
+        auth_config::AuthConfigExtensions, mirror_config::MirrorConfigExtensions,
+        package_dependency::PackageDependencyExtensions,
+        package_metadata::PackageMetadataExtensions,
+        shared_package_config::SharedPackageConfigExtensions,
+    },
+    models::{
+        artifact::ArtifactKind,
+        auth::{AuthConfig, AuthScheme},
+        dependency::{SharedDependency, SharedPackageConfig},
+        extra::DependencyLibType,
+        mirror::MirrorConfig,
+        package::PackageConfig,
+        source::{DependencySource, GitReference},
+    },
+};
+
+pub const SOURCES_DIR_NAME: &str = "src";
+pub const INCLUDES_DIR_NAME: &str = "includes";
+pub const LIBS_DIR_NAME: &str = "libs";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum DownloadPlanError {
+    #[error("the package config of {0} is required to restore it")]
+    MissingPackage(String),
+
+    #[error("{0} has no url to download its source from")]
+    MissingSource(String),
+
+    #[error("{id} is linked as {lib_type:?} but has no {kind:?} link")]
+    MissingLink {
+        id: String,
+        lib_type: DependencyLibType,
+        kind: ArtifactKind,
+    },
+
+    #[error(
+        "{id} contains the unsafe path {path:?}, which could leave the dependencies directory"
+    )]
+    UnsafePath { id: String, path: PathBuf },
+
+    #[error("{url} of {id} has no file name to download to")]
+    MissingFileName { id: String, url: String },
+}
+
+/// Where the source of a dependency is fetched from
+#[derive(Clone, PartialEq, Eq)]
+pub enum SourceLocation {
+    /// A git repository, checked out at a reference or the default branch
+    Git {
+        url: String,
+        reference: Option<GitReference>,
+    },
+    /// A zip archive
+    Zip { url: String },
+    /// A directory on disk
+    Local { path: PathBuf },
+}
+
+/// A single step of restoring dependencies.
+/// Paths are under `PackageConfig::dependencies_dir` unless they come from the local file system.
+///
+/// `auth` holds the authentication required by the urls of a download, keyed by url.
+/// It only references secrets, and urls are redacted in the debug output so plans can be logged.
+#[derive(Clone, PartialEq, Eq)]
+pub enum DownloadAction {
+    /// Fetches the source of a dependency to `dest`.
+    /// Only `sub_folder` of the download is kept when set, so one repository can contain multiple packages.
+    /// `fallback_urls` replace the url of the source, in order, when it can't be fetched.
+    FetchSource {
+        id: String,
+        source: SourceLocation,
+        fallback_urls: Vec<String>,
+        auth: BTreeMap<String, AuthScheme>,
+        sub_folder: Option<String>,
+        dest: PathBuf,
+    },
+
+    /// Copies a file or directory out of a fetched source
+    Copy {
+        id: String,
+        from: PathBuf,
+        to: PathBuf,
+    },
+
+    /// Downloads a binary artifact, which should match `sha256` when it is known.
+    /// `fallback_urls` are tried in order when `url` can't be downloaded.
+    DownloadArtifact {
+        id: String,
+        kind: ArtifactKind,
+        url: String,
+        fallback_urls: Vec<String>,
+        auth: BTreeMap<String, AuthScheme>,
+        sha256: Option<String>,
+        dest: PathBuf,
+    },
+
+    /// Downloads an extra file given as a url
+    DownloadFile {
+        id: String,
+        url: String,
+        fallback_urls: Vec<String>,
+        auth: BTreeMap<String, AuthScheme>,
+        dest: PathBuf,
+    },
+}
+
+impl DownloadAction {
+    pub fn get_id(&self) -> &str {
+        match self {
+            DownloadAction::FetchSource { id, .. }
+            | DownloadAction::Copy { id, .. }
+            | DownloadAction::DownloadArtifact { id, .. }
+            | DownloadAction::DownloadFile { id, .. } => id,
+        }
+    }
+
+    /// The path this action writes to
+    pub fn get_dest(&self) -> &PathBuf {
+        match self {
+            DownloadAction::FetchSource { dest, .. }
+            | DownloadAction::Copy { to: dest, .. }
+            | DownloadAction::DownloadArtifact { dest, .. }
+            | DownloadAction::DownloadFile { dest, .. } => dest,
+        }
+    }
+}
+
+impl Debug for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceLocation::Git { url, reference } => f
+                .debug_struct("Git")
+                .field("url", &redact_url(url))
+                .field("reference", reference)
+                .finish(),
+            SourceLocation::Zip { url } => f
+                .debug_struct("Zip")
+                .field("url", &redact_url(url))
+                .finish(),
+            SourceLocation::Local { path } => f.debug_struct("Local").field("path", path).finish(),
+        }
+    }
+}
+
+impl Debug for DownloadAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn redact_urls(urls: &[String]) -> Vec<Cow<'_, str>> {
+            urls.iter().map(|u| redact_url(u)).collect()
+        }
+        fn redact_auth(auth: &BTreeMap<String, AuthScheme>) -> Vec<(Cow<'_, str>, &AuthScheme)> {
+            auth.iter()
+                .map(|(url, scheme)| (redact_url(url), scheme))
+                .collect()
+        }
+
+        match self {
+            DownloadAction::FetchSource {
+                id,
+                source,
+                fallback_urls,
+                auth,
+                sub_folder,
+                dest,
+            } => f
+                .debug_struct("FetchSource")
+                .field("id", id)
+                .field("source", source)
+                .field("fallback_urls", &redact_urls(fallback_urls))
+                .field("auth", &redact_auth(auth))
+                .field("sub_folder", sub_folder)
+                .field("dest", dest)
+                .finish(),
+            DownloadAction::Copy { id, from, to } => f
+                .debug_struct("Copy")
+                .field("id", id)
+                .field("from", from)
+                .field("to", to)
+                .finish(),
+            DownloadAction::DownloadArtifact {
+                id,
+                kind,
+                url,
+                fallback_urls,
+                auth,
+                sha256,
+                dest,
+            } => f
+                .debug_struct("DownloadArtifact")
+                .field("id", id)
+                .field("kind", kind)
+                .field("url", &redact_url(url))
+                .field("fallback_urls", &redact_urls(fallback_urls))
+                .field("auth", &redact_auth(auth))
+                .field("sha256", sha256)
+                .field("dest", dest)
+                .finish(),
+            DownloadAction::DownloadFile {
+                id,
+                url,
+                fallback_urls,
+                auth,
+                dest,
+            } => f
+                .debug_struct("DownloadFile")
+                .field("id", id)
+                .field("url", &redact_url(url))
+                .field("fallback_urls", &redact_urls(fallback_urls))
+                .field("auth", &redact_auth(auth))
+                .field("dest", dest)
+                .finish(),
+        }
+    }
+}
+
+/// Whether an extra file is a url to download rather than a path in the source of the dependency.
+/// Only `http://` and `https://` urls are downloaded, other schemes such as `file://` are rejected as unsafe paths.
+pub fn is_url(file: &str) -> bool {
+    let scheme = file.split_once("://").map(|(scheme, _)| scheme);
+    scheme.is_some_and(|scheme| {
+        scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("https")
+    })
+}
+
+/// The last path segment of a url, which downloaded extra files are named after
+pub fn get_url_file_name(url: &str) -> &str {
+    url.split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .unwrap_or_default()
+}
+
+/// Whether a path from a package config stays inside the directory it is joined to.
+/// Absolute paths, drive prefixes and `..` segments are rejected with either separator,
+/// since package configs are written on any platform.
+pub fn is_safe_relative_path(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    !path.starts_with(['/', '\\'])
+        && !path.contains(':')
+        && path.split(['/', '\\']).all(|segment| segment != "..")
+}
+
+/// Whether a name from a package config is a single path segment, such as a package id or library name
+pub fn is_safe_file_name(name: &Path) -> bool {
+    let is_safe = is_safe_relative_path(name);
+    let name = name.to_string_lossy();
+    is_safe && !name.is_empty() && name != "." && !name.contains(['/', '\\'])
+}
+
+/// `path` if it is a safe relative path, see [`is_safe_relative_path`]
+fn check_path<'a>(id: &str, path: &'a Path) -> Result<&'a Path, DownloadPlanError> {
+    match is_safe_relative_path(path) {
+        true => Ok(path),
+        false => Err(DownloadPlanError::UnsafePath {
+            id: id.to_string(),
+            path: path.to_path_buf(),
+        }),
+    }
+}
+
+/// `name` if it is a single path segment, see [`is_safe_file_name`]
+fn check_file_name<'a>(id: &str, name: &'a Path) -> Result<&'a Path, DownloadPlanError> {
+    match is_safe_file_name(name) {
+        true => Ok(name),
+        false => Err(DownloadPlanError::UnsafePath {
+            id: id.to_string(),
+            path: name.to_path_buf(),
+        }),
+    }
+}
+
+/// The file name of the debug .so of a dependency, stored next to the release .so
+pub fn get_debug_so_name(dependency: &SharedDependency) -> PathBuf {
+    let so_name = dependency.get_so_name2();
+    format!("debug_{}", so_name.to_string_lossy()).into()
+}
+
+/// Computes the actions needed to restore the dependencies of `shared`, in order.
+///
+/// `packages` holds the package config of every restored dependency by id, which provides the source url
+/// and the shared directory whose headers are copied. Nothing is downloaded or written.
+///
+/// For each dependency, its source is fetched to `src/{id}` first, then the shared directory and the
+/// extra files requested by the root package are copied or downloaded to `includes/{id}`, followed by the binary
+/// chosen by its lib type in `libs/` and the debug .so when there is one. Header only dependencies
+/// download no binaries.
+///
+/// Urls are rewritten to their `mirrors`, keeping the remaining urls as fallbacks, and every url then
+/// records the scheme of the `auth` rule matching it.
+///
+/// Ids, directories and file names come from remote package configs, so any that could be written outside of
+/// the dependencies directory fail with [`DownloadPlanError::UnsafePath`].
+pub fn get_download_plan(
+    shared: &SharedPackageConfig,
+    packages: &BTreeMap<String, PackageConfig>,
+    mirrors: &MirrorConfig,
+    auth: &AuthConfig,
+) -> Result<Vec<DownloadAction>, DownloadPlanError> {
+    let dependencies_dir = &shared.config.dependencies_dir;
+    let mut actions = vec![];
+
+    for dependency in &shared.restored_dependencies {
+        let id = &dependency.dependency.id;
+        #[allow(deprecated)]
+        let metadata = &dependency.dependency.additional_data;
+        let modifier = shared.get_dependency_modifier(dependency);
+        let package = packages
+            .get(id)
+            .ok_or_else(|| DownloadPlanError::MissingPackage(id.clone()))?;
+
+        let (source, sub_folder) = get_source_location(shared, dependency, package)?;
+        if let Some(sub_folder) = &sub_folder {
+            check_path(id, Path::new(sub_folder))?;
+        }
+
+        let dir_name = check_file_name(id, Path::new(id))?;
+        let src_dir = dependencies_dir.join(SOURCES_DIR_NAME).join(dir_name);
+        let include_dir = dependencies_dir.join(INCLUDES_DIR_NAME).join(dir_name);
+        actions.push(DownloadAction::FetchSource {
+            id: id.clone(),
+            source,
+            fallback_urls: vec![],
+            auth: BTreeMap::new(),
+            sub_folder,
+            dest: src_dir.clone(),
+        });
+
+        let shared_dir = check_path(id, &package.shared_dir)?;
+        actions.push(DownloadAction::Copy {
+            id: id.clone(),
+            from: src_dir.join(shared_dir),
+            to: include_dir.join(shared_dir),
+        });
+        for file in modifier
+            .and_then(|m| m.extra_files.as_ref())
+            .into_iter()
+            .flatten()
+        {
+            if !is_url(file) {
+                let file = check_path(id, Path::new(file))?;
+                actions.push(DownloadAction::Copy {
+                    id: id.clone(),
+                    from: src_dir.join(file),
+                    to: include_dir.join(file),
+                });
+                continue;
+            }
+
+            let file_name = get_url_file_name(file);
+            if file_name.is_empty() {
+                return Err(DownloadPlanError::MissingFileName {
+                    id: id.clone(),
+                    url: redact_url(file).into_owned(),
+                });
+            }
+            actions.push(DownloadAction::DownloadFile {
+                id: id.clone(),
+                url: file.clone(),
+                fallback_urls: vec![],
+                auth: BTreeMap::new(),
+                dest: include_dir.join(check_file_name(id, Path::new(file_name))?),
+            });
+        }
+
+        let libs_dir = dependencies_dir.join(LIBS_DIR_NAME);
+        let lib_type = shared.get_dependency_lib_type(dependency);
+        let (kind, name) = match lib_type {
+            DependencyLibType::Shared => (ArtifactKind::So, dependency.get_so_name2()),
+            DependencyLibType::Static => (ArtifactKind::Static, dependency.get_static_name2()),
+            DependencyLibType::HeaderOnly => continue,
+        };
+
+        let url = metadata
+            .get_link(kind)
+            .ok_or_else(|| DownloadPlanError::MissingLink {
+                id: id.clone(),
+                lib_type: lib_type.clone(),
+                kind,
+            })?;
+        actions.push(get_artifact_action(
+            dependency,
+            kind,
+            url,
+            libs_dir.join(check_file_name(id, &name)?),
+        ));
+
+        if lib_type == DependencyLibType::Shared {
+            if let Some(url) = metadata.get_link(ArtifactKind::DebugSo) {
+                let name = get_debug_so_name(dependency);
+                let dest = libs_dir.join(check_file_name(id, &name)?);
+                actions.push(get_artifact_action(
+                    dependency,
+                    ArtifactKind::DebugSo,
+                    url,
+                    dest,
+                ));
+            }
+        }
+    }
+
+    // auth is matched against the mirrored urls
+    mirrors.apply_to_download_plan(&mut actions);
+    set_auth(auth, &mut actions);
+    Ok(actions)
+}
+
+/// Records the auth required by every url of the actions, including fallbacks
+fn set_auth(config: &AuthConfig, actions: &mut [DownloadAction]) {
+    for action in actions {
+        let (url, fallback_urls, auth) = match action {
+            DownloadAction::FetchSource {
+                source: SourceLocation::Git { url, .. } | SourceLocation::Zip { url },
+                fallback_urls,
+                auth,
+                ..
+            }
+            | DownloadAction::DownloadArtifact {
+                url,
+                fallback_urls,
+                auth,
+                ..
+            }
+            | DownloadAction::DownloadFile {
+                url,
+                fallback_urls,
+                auth,
+                ..
+            } => (url, fallback_urls, auth),
+            DownloadAction::FetchSource { .. } | DownloadAction::Copy { .. } => continue,
+        };
+
+        auth.clear();
+        for url in [&*url].into_iter().chain(fallback_urls.iter()) {
+            if let Some(scheme) = config.get_scheme(url) {
+                auth.insert(url.clone(), scheme.clone());
+            }
+        }
+    }
+}
+
+/// Where the source of a restored dependency is fetched from and the sub folder of it to use.
+/// The source recorded in the lockfile takes precedence over the one declared by the root package.
+/// Packages from a registry are fetched from the url they publish.
+pub fn get_source_location(
+    shared: &SharedPackageConfig,
+    dependency: &SharedDependency,
+    package: &PackageConfig,
+) -> Result<(SourceLocation, Option<String>), DownloadPlanError> {
+    let id = &dependency.dependency.id;
+    #[allow(deprecated)]
+    let metadata = &dependency.dependency.additional_data;
+    let declared = shared
+        .get_declared_dependency(dependency)
+        .map(|d| d.get_source());
+
+    let source = dependency
+        .source
+        .clone()
+        .or(declared)
+        .filter(|s| !matches!(s, DependencySource::Registry { .. }))
+        .or_else(|| DependencySource::from_legacy_metadata(package.info.url.as_deref(), metadata))
+        .ok_or_else(|| DownloadPlanError::MissingSource(id.clone()))?;
+
+    let sub_folder = source
+        .get_sub_folder()
+        .or(metadata.sub_folder.as_ref())
+        .cloned();
+    let location = match source {
+        DependencySource::Git { url, reference, .. } => SourceLocation::Git { url, reference },
+        DependencySource::Url { url, .. } => SourceLocation::Zip { url },
+        DependencySource::Path { path } => SourceLocation::Local { path },
+        DependencySource::Registry { .. } => {
+            return Err(DownloadPlanError::MissingSource(id.clone()))
+        }
+    };
+
+    Ok((location, sub_folder))
+}
+
+fn get_artifact_action(
+    dependency: &SharedDependency,
+    kind: ArtifactKind,
+    url: &str,
+    dest: PathBuf,
+) -> DownloadAction {
+    // the digest recorded on a previous restore takes precedence over the published one
+    #[allow(deprecated)]
+    let sha256 = dependency
+        .checksums
+        .get(&kind)
+        .or_else(|| dependency.dependency.additional_data.get_sha256(kind))
+        .cloned();
+
+    DownloadAction::DownloadArtifact {
+        id: dependency.dependency.id.clone(),
+        kind,
+        url: url.to_string(),
+        fallback_urls: vec![],
+        auth: BTreeMap::new(),
+        sha256,
+        dest,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::{Version, VersionReq};
+
+    use super::*;
+    use crate::models::{
+        dependency::Dependency, extra::AdditionalPackageMetadata, mirror::MirrorRule,
+        package::PackageDependency,
+    };
+
+    /// A root package depending on `dep`, which is restored from a zip and links a shared library
+    fn get_shared(
+        extra_files: Vec<&str>,
+    ) -> (SharedPackageConfig, BTreeMap<String, PackageConfig>) {
+        let mut root = PackageConfig {
+            dependencies_dir: "extern".into(),
+            ..Default::default()
+        };
+        let mut declared = PackageDependency {
+            id: "dep".to_string(),
+            version_range: VersionReq::parse("^1.0.0").unwrap(),
+            source: None,
+            additional_data: Default::default(),
+        };
+        declared.additional_data.extra_files =
+            Some(extra_files.into_iter().map(str::to_string).collect());
+        root.dependencies.push(declared);
+
+        let metadata = AdditionalPackageMetadata {
+            so_link: Some("https://files.test/libdep.so".to_string()),
+            ..Default::default()
+        };
+        #[allow(deprecated)]
+        let restored = SharedDependency {
+            dependency: Dependency {
+                id: "dep".to_string(),
+                version_range: VersionReq::parse("^1.0.0").unwrap(),
+                additional_data: metadata,
+            },
+            version: Version::new(1, 0, 0),
+            checksums: BTreeMap::new(),
+            publisher_key: None,
+            source: Some(DependencySource::Url {
+                url: "https://files.test/dep.zip".to_string(),
+                sub_folder: None,
+            }),
+            required_by: vec![],
+            declared_id: None,
+            applied_override: None,
+        };
+
+        let mut package = PackageConfig {
+            shared_dir: "shared".into(),
+            ..Default::default()
+        };
+        package.info.id = "dep".to_string();
+        let packages = BTreeMap::from([("dep".to_string(), package)]);
+
+        let shared = SharedPackageConfig {
+            config: root,
+            restored_dependencies: vec![restored],
+        };
+        (shared, packages)
+    }
+
+    fn get_plan(
+        shared: &SharedPackageConfig,
+        packages: &BTreeMap<String, PackageConfig>,
+    ) -> Result<Vec<DownloadAction>, DownloadPlanError> {
+        get_download_plan(
+            shared,
+            packages,
+            &MirrorConfig::default(),
+            &AuthConfig::default(),
+        )
+    }
+
+    #[test]
+    fn plans_downloads() {
+        let (shared, packages) =
+            get_shared(vec!["extra.hpp", "https://files.test/extra/tool.h?v=1"]);
+        let mirrors = MirrorConfig {
+            rules: vec![MirrorRule {
+                prefix: "https://files.test/".to_string(),
+                mirrors: vec!["https://mirror.test/".to_string()],
+                fallback_to_original: None,
+            }],
+        };
+
+        let actions =
+            get_download_plan(&shared, &packages, &mirrors, &AuthConfig::default()).unwrap();
+        let dests: Vec<_> = actions
+            .iter()
+            .map(|action| match action {
+                DownloadAction::FetchSource { dest, .. }
+                | DownloadAction::DownloadArtifact { dest, .. }
+                | DownloadAction::DownloadFile { dest, .. } => dest.clone(),
+                DownloadAction::Copy { to, .. } => to.clone(),
+            })
+            .collect();
+        assert_eq!(
+            dests,
+            [
+                "extern/src/dep",
+                "extern/includes/dep/shared",
+                "extern/includes/dep/extra.hpp",
+                "extern/includes/dep/tool.h",
+                "extern/libs/libdep_1_0_0.so",
+            ]
+            .map(PathBuf::from)
+        );
+
+        let Some(DownloadAction::DownloadArtifact {
+            url, fallback_urls, ..
+        }) = actions.last()
+        else {
+            panic!("the library is downloaded last");
+        };
+        assert_eq!(url, "https://mirror.test/libdep.so");
+        assert_eq!(fallback_urls, &["https://files.test/libdep.so"]);
+    }
+
+    #[test]
+    fn rejects_unsafe_paths() {
+        let unsafe_path = |path: &str| DownloadPlanError::UnsafePath {
+            id: "dep".to_string(),
+            path: path.into(),
+        };
+
+        for file in [
+            "../escaped.hpp",
+            "/etc/passwd",
+            "a\\..\\..\\b",
+            "C:/file",
+            "file://etc/passwd",
+        ] {
+            let (shared, packages) = get_shared(vec![file]);
+            assert_eq!(
+                get_plan(&shared, &packages),
+                Err(unsafe_path(file)),
+                "{file}"
+            );
+        }
+
+        let (shared, packages) = get_shared(vec!["https://files.test/"]);
+        assert_eq!(
+            get_plan(&shared, &packages),
+            Err(DownloadPlanError::MissingFileName {
+                id: "dep".to_string(),
+                url: "https://files.test/".to_string(),
+            })
+        );
+
+        let (mut shared, packages) = get_shared(vec![]);
+        #[allow(deprecated)]
+        let metadata = &mut shared.restored_dependencies[0].dependency.additional_data;
+        metadata.override_so_name = Some("../libescaped.so".to_string());
+        assert_eq!(
+            get_plan(&shared, &packages),
+            Err(unsafe_path("../libescaped.so"))
+        );
+
+        let (shared, mut packages) = get_shared(vec![]);
+        packages.get_mut("dep").unwrap().shared_dir = "../../shared".into();
+        assert_eq!(
+            get_plan(&shared, &packages),
+            Err(unsafe_path("../../shared"))
+        );
+    }
+
+    #[test]
+    fn checks_file_names() {
+        for name in ["lib.so", "my-package", ".hidden"] {
+            assert!(is_safe_file_name(Path::new(name)), "{name}");
+        }
+        for name in ["", ".", "..", "a/b", "a\\b", "/abs", "C:lib.so"] {
+            assert!(!is_safe_file_name(Path::new(name)), "{name}");
+        }
+    }
+}
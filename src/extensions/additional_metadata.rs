@@ -0,0 +1,257 @@
+use crate::models::{
+    artifact::ArtifactKind,
+    extra::{AdditionalPackageMetadata, DependencyLibType},
+};
+
+use super::compile_options::CompileOptionsExtensions;
+
+pub const DEBUG_PROFILE_NAME: &str = "debug";
+pub const RELEASE_PROFILE_NAME: &str = "release";
+
+pub trait AdditionalPackageMetadataExtensions {
+    /// The lib type the package declares for itself
+    fn get_lib_type(&self) -> DependencyLibType;
+
+    /// The link to download an artifact from. Source archives are not linked in the metadata.
+    fn get_link(&self, kind: ArtifactKind) -> Option<&String>;
+
+    /// The published SHA-256 digest of an artifact
+    fn get_sha256(&self, kind: ArtifactKind) -> Option<&String>;
+
+    /// The effective metadata when building with the given profile.
+    /// Profiles that are not declared only apply their defaults.
+    fn with_profile(&self, profile: &str) -> AdditionalPackageMetadata;
+}
+
+impl AdditionalPackageMetadataExtensions for AdditionalPackageMetadata {
+    fn get_lib_type(&self) -> DependencyLibType {
+        #[allow(deprecated)]
+        let static_linking = self.static_linking.unwrap_or(false);
+
+        if self.headers_only.unwrap_or(false) {
+            DependencyLibType::HeaderOnly
+        } else if static_linking || (self.static_link.is_some() && self.so_link.is_none()) {
+            DependencyLibType::Static
+        } else {
+            DependencyLibType::Shared
+        }
+    }
+
+    fn get_link(&self, kind: ArtifactKind) -> Option<&String> {
+        match kind {
+            ArtifactKind::So => self.so_link.as_ref(),
+            ArtifactKind::DebugSo => self.debug_so_link.as_ref(),
+            ArtifactKind::Static => self.static_link.as_ref(),
+            ArtifactKind::Mod => self.mod_link.as_ref(),
+            ArtifactKind::Source => None,
+        }
+    }
+
+    fn get_sha256(&self, kind: ArtifactKind) -> Option<&String> {
+        match kind {
+            ArtifactKind::So => self.so_sha256.as_ref(),
+            ArtifactKind::DebugSo => self.debug_so_sha256.as_ref(),
+            ArtifactKind::Static => self.static_sha256.as_ref(),
+            ArtifactKind::Mod => self.mod_sha256.as_ref(),
+            ArtifactKind::Source => None,
+        }
+    }
+
+    fn with_profile(&self, profile: &str) -> AdditionalPackageMetadata {
+        let mut metadata = self.clone();
+        let build_profile = self.profiles.as_ref().and_then(|p| p.get(profile));
+
+        if let Some(build_profile) = build_profile {
+            if let Some(compile_options) = &build_profile.compile_options {
+                metadata
+                    .compile_options
+                    .get_or_insert_with(Default::default)
+                    .apply(
+                        compile_options,
+                        build_profile.compile_options_mode.unwrap_or_default(),
+                    );
+            }
+
+            // an overridden link invalidates the digest and signature of the link it replaces
+            let mut overridden = vec![];
+            if build_profile.so_link.is_some() {
+                metadata.so_link.clone_from(&build_profile.so_link);
+                metadata.so_sha256.clone_from(&build_profile.so_sha256);
+                overridden.push(ArtifactKind::So);
+            }
+            if build_profile.static_link.is_some() {
+                metadata.static_link.clone_from(&build_profile.static_link);
+                metadata
+                    .static_sha256
+                    .clone_from(&build_profile.static_sha256);
+                overridden.push(ArtifactKind::Static);
+            }
+            if build_profile.debug_so_link.is_some() {
+                metadata
+                    .debug_so_link
+                    .clone_from(&build_profile.debug_so_link);
+                metadata
+                    .debug_so_sha256
+                    .clone_from(&build_profile.debug_so_sha256);
+                overridden.push(ArtifactKind::DebugSo);
+            }
+
+            for kind in overridden {
+                let signature = build_profile
+                    .signatures
+                    .as_ref()
+                    .and_then(|s| s.get(&kind))
+                    .cloned();
+                set_signature(&mut metadata, kind, signature);
+            }
+        }
+
+        let link_debug_so = build_profile
+            .and_then(|p| p.link_debug_so)
+            .unwrap_or(profile == DEBUG_PROFILE_NAME);
+        if link_debug_so && metadata.debug_so_link.is_some() {
+            metadata.so_link.clone_from(&metadata.debug_so_link);
+            metadata.so_sha256.clone_from(&metadata.debug_so_sha256);
+
+            let signature = metadata
+                .signatures
+                .as_ref()
+                .and_then(|s| s.get(&ArtifactKind::DebugSo))
+                .cloned();
+            set_signature(&mut metadata, ArtifactKind::So, signature);
+        }
+
+        metadata
+    }
+}
+
+fn set_signature(
+    metadata: &mut AdditionalPackageMetadata,
+    kind: ArtifactKind,
+    signature: Option<String>,
+) {
+    match signature {
+        Some(signature) => {
+            metadata
+                .signatures
+                .get_or_insert_with(Default::default)
+                .insert(kind, signature);
+        }
+        None => {
+            if let Some(signatures) = &mut metadata.signatures {
+                signatures.remove(&kind);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::extra::{BuildProfile, CompileOptions, CompileOptionsMergeMode};
+
+    fn flags(flags: &[&str]) -> Option<Vec<String>> {
+        Some(flags.iter().map(|f| f.to_string()).collect())
+    }
+
+    fn metadata() -> AdditionalPackageMetadata {
+        AdditionalPackageMetadata {
+            so_link: Some("https://files.test/release.so".to_string()),
+            so_sha256: Some("release".to_string()),
+            debug_so_link: Some("https://files.test/debug.so".to_string()),
+            debug_so_sha256: Some("debug".to_string()),
+            signatures: Some(
+                [
+                    (ArtifactKind::So, "release signature".to_string()),
+                    (ArtifactKind::DebugSo, "debug signature".to_string()),
+                ]
+                .into(),
+            ),
+            compile_options: Some(CompileOptions {
+                cpp_flags: flags(&["-O2"]),
+                ..Default::default()
+            }),
+            profiles: Some(
+                [
+                    (
+                        RELEASE_PROFILE_NAME.to_string(),
+                        BuildProfile {
+                            compile_options: Some(CompileOptions {
+                                cpp_flags: flags(&["-flto"]),
+                                defines: Some([("NDEBUG".to_string(), None)].into()),
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        },
+                    ),
+                    (
+                        DEBUG_PROFILE_NAME.to_string(),
+                        BuildProfile {
+                            compile_options: Some(CompileOptions {
+                                cpp_flags: flags(&["-O0", "-g"]),
+                                ..Default::default()
+                            }),
+                            compile_options_mode: Some(CompileOptionsMergeMode::Replace),
+                            ..Default::default()
+                        },
+                    ),
+                    (
+                        "asan".to_string(),
+                        BuildProfile {
+                            so_link: Some("https://files.test/asan.so".to_string()),
+                            so_sha256: Some("asan".to_string()),
+                            ..Default::default()
+                        },
+                    ),
+                ]
+                .into(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn extends_or_replaces_compile_options_of_profiles() {
+        let metadata = metadata();
+
+        let release = metadata.with_profile(RELEASE_PROFILE_NAME);
+        let compile_options = release.compile_options.unwrap();
+        assert_eq!(compile_options.cpp_flags, flags(&["-O2", "-flto"]));
+        assert_eq!(compile_options.get_definitions(), ["NDEBUG"]);
+        assert_eq!(release.so_link, metadata.so_link);
+
+        let debug = metadata.with_profile(DEBUG_PROFILE_NAME);
+        assert_eq!(
+            debug.compile_options.unwrap().cpp_flags,
+            flags(&["-O0", "-g"])
+        );
+
+        // undeclared profiles only apply their defaults
+        assert_eq!(metadata.with_profile("custom"), metadata);
+    }
+
+    #[test]
+    fn links_artifacts_of_profiles() {
+        let metadata = metadata();
+
+        // the debug profile links the debug .so by default
+        let debug = metadata.with_profile(DEBUG_PROFILE_NAME);
+        assert_eq!(debug.so_link, metadata.debug_so_link);
+        assert_eq!(debug.so_sha256, metadata.debug_so_sha256);
+        assert_eq!(
+            debug.signatures.unwrap().get(&ArtifactKind::So).unwrap(),
+            "debug signature"
+        );
+
+        // an overridden link drops the signature of the link it replaces
+        let asan = metadata.with_profile("asan");
+        assert_eq!(
+            asan.get_link(ArtifactKind::So).unwrap(),
+            "https://files.test/asan.so"
+        );
+        assert_eq!(asan.get_sha256(ArtifactKind::So).unwrap(), "asan");
+        let signatures = asan.signatures.unwrap();
+        assert!(!signatures.contains_key(&ArtifactKind::So));
+        assert!(signatures.contains_key(&ArtifactKind::DebugSo));
+    }
+}
@@ -0,0 +1,237 @@
+use url::Url;
+
+use crate::{
+    credentials::{get_credential_provider, get_url_host, Credential, CredentialError},
+    models::auth::{AuthConfig, AuthRule, AuthScheme},
+};
+
+pub trait AuthConfigExtensions {
+    /// The scheme of the first rule matching a url, `None` if the url is not authenticated.
+    /// Only https urls are authenticated, so secrets are never sent in plain text.
+    fn get_scheme(&self, url: &str) -> Option<&AuthScheme>;
+}
+
+impl AuthConfigExtensions for AuthConfig {
+    fn get_scheme(&self, url: &str) -> Option<&AuthScheme> {
+        let parsed = Url::parse(url).ok().filter(|url| url.scheme() == "https")?;
+        let host = get_url_host(url)?;
+        let path = parsed.path();
+
+        self.rules
+            .iter()
+            .find(|rule| matches_host(rule, &host) && matches_path(rule, path))
+            .map(|rule| &rule.scheme)
+    }
+}
+
+fn matches_host(rule: &AuthRule, host: &str) -> bool {
+    let rule_host = rule.host.to_ascii_lowercase();
+    match rule_host.strip_prefix('*') {
+        Some(suffix) if suffix.starts_with('.') => host.ends_with(suffix),
+        _ => host == rule_host,
+    }
+}
+
+/// Matches the prefix on whole segments of the normalized path,
+/// so `/my-org` matches neither `/my-org-other/` nor `/my-org/../other/`
+fn matches_path(rule: &AuthRule, path: &str) -> bool {
+    let Some(prefix) = &rule.path_prefix else {
+        return true;
+    };
+
+    let path = get_path_segments(path);
+    let prefix = get_path_segments(prefix);
+    path.starts_with(&prefix)
+}
+
+/// The segments of a url path without its query, percent-decoded with dot segments resolved
+fn get_path_segments(path: &str) -> Vec<String> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let path = percent_decode(path);
+
+    let mut segments: Vec<String> = vec![];
+    // servers may treat a backslash as a separator
+    for segment in path.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment.to_string()),
+        }
+    }
+    segments
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+pub trait AuthSchemeExtensions {
+    /// The name and value of the header authenticating a request to a url, reading the secret it references.
+    /// `None` if the secret is not set, the request is then sent without authentication.
+    fn get_header(&self, url: &str) -> Result<Option<(String, String)>, CredentialError>;
+}
+
+impl AuthSchemeExtensions for AuthScheme {
+    fn get_header(&self, url: &str) -> Result<Option<(String, String)>, CredentialError> {
+        let reference = match self {
+            AuthScheme::Bearer { token } => token,
+            AuthScheme::Basic { password, .. } => password,
+            AuthScheme::Header { value, .. } => value,
+        };
+
+        let Some(secret) = get_credential_provider(reference).get_credential(url)? else {
+            return Ok(None);
+        };
+        let secret = secret.password;
+
+        Ok(Some(match self {
+            AuthScheme::Bearer { .. } => ("Authorization".to_string(), format!("Bearer {secret}")),
+            AuthScheme::Basic { username, .. } => {
+                let credential = Credential {
+                    username: Some(username.clone()),
+                    password: secret,
+                };
+                ("Authorization".to_string(), credential.get_authorization())
+            }
+            AuthScheme::Header { name, .. } => (name.clone(), secret),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::user_config::TokenReference;
+
+    fn rule(host: &str, path_prefix: Option<&str>, name: &str) -> AuthRule {
+        AuthRule {
+            host: host.to_string(),
+            path_prefix: path_prefix.map(str::to_string),
+            scheme: AuthScheme::Bearer {
+                token: TokenReference::Env {
+                    name: name.to_string(),
+                },
+            },
+        }
+    }
+
+    fn get_token_name(config: &AuthConfig, url: &str) -> Option<String> {
+        match config.get_scheme(url)? {
+            AuthScheme::Bearer {
+                token: TokenReference::Env { name },
+            } => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn matches_hosts() {
+        let config = AuthConfig {
+            rules: vec![
+                rule("GitHub.com", None, "GITHUB"),
+                rule("*.example.com", None, "EXAMPLE"),
+                rule("files.test:8443", None, "PORT"),
+            ],
+        };
+
+        let token = |url| get_token_name(&config, url);
+        assert_eq!(
+            token("https://github.com/owner/repo").as_deref(),
+            Some("GITHUB")
+        );
+        assert_eq!(
+            token("https://user@GITHUB.COM/x").as_deref(),
+            Some("GITHUB")
+        );
+        assert_eq!(token("https://api.github.com/x"), None);
+        assert_eq!(
+            token("https://cdn.example.com/x").as_deref(),
+            Some("EXAMPLE")
+        );
+        assert_eq!(
+            token("https://a.b.example.com/x").as_deref(),
+            Some("EXAMPLE")
+        );
+        assert_eq!(token("https://example.com/x"), None);
+        assert_eq!(token("https://badexample.com/x"), None);
+        assert_eq!(token("https://files.test:8443/x").as_deref(), Some("PORT"));
+        assert_eq!(token("https://files.test/x"), None);
+    }
+
+    #[test]
+    fn ignores_hosts_hidden_in_user_info_and_plain_http() {
+        let config = AuthConfig {
+            rules: vec![rule("github.com", None, "GITHUB")],
+        };
+
+        let token = |url| get_token_name(&config, url);
+        assert_eq!(token("https://evil.com\\@github.com/lib.so"), None);
+        assert_eq!(token("https://evil.com\\github.com/lib.so"), None);
+        assert_eq!(token("https://github.com.evil.com/lib.so"), None);
+        assert_eq!(token("http://github.com/lib.so"), None);
+        assert_eq!(
+            token("https://evil.com@github.com/lib.so").as_deref(),
+            Some("GITHUB")
+        );
+    }
+
+    #[test]
+    fn matches_path_prefixes_on_segments() {
+        let config = AuthConfig {
+            rules: vec![
+                rule("github.com", Some("/my-org"), "ORG"),
+                rule("github.com", Some("/"), "ANY"),
+            ],
+        };
+
+        let token = |url| get_token_name(&config, url);
+        assert_eq!(
+            token("https://github.com/my-org/repo").as_deref(),
+            Some("ORG")
+        );
+        assert_eq!(token("https://github.com/my-org").as_deref(), Some("ORG"));
+        assert_eq!(
+            token("https://github.com/./my-org/repo").as_deref(),
+            Some("ORG")
+        );
+        assert_eq!(
+            token("https://github.com/%6Dy-org/repo").as_deref(),
+            Some("ORG")
+        );
+        assert_eq!(
+            token("https://github.com/my-org-other/repo").as_deref(),
+            Some("ANY")
+        );
+        assert_eq!(
+            token("https://github.com/my-org/../other").as_deref(),
+            Some("ANY")
+        );
+        assert_eq!(
+            token("https://github.com/my-org%2F..%2Fother").as_deref(),
+            Some("ANY")
+        );
+        assert_eq!(token("https://github.com?/my-org").as_deref(), Some("ANY"));
+    }
+}
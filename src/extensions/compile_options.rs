@@ -0,0 +1,168 @@
+use crate::models::extra::{CompileOptions, CompileOptionsMergeMode};
+
+pub trait CompileOptionsExtensions {
+    /// Appends the options of `other` to these options.
+    /// Include paths and libraries that are already present are skipped and defines of `other` take precedence.
+    /// Flags are appended as is since their meaning may depend on their position.
+    fn merge(&mut self, other: &CompileOptions);
+
+    /// Replaces the options that are specified in `other`, leaving the others untouched
+    fn replace(&mut self, other: &CompileOptions);
+
+    /// Combines `other` into these options according to `mode`
+    fn apply(&mut self, other: &CompileOptions, mode: CompileOptionsMergeMode) {
+        match mode {
+            CompileOptionsMergeMode::Extend => self.merge(other),
+            CompileOptionsMergeMode::Replace => self.replace(other),
+        }
+    }
+
+    /// The preprocessor defines as `NAME` or `NAME=VALUE`, as accepted by `-D` and CMake's `target_compile_definitions`
+    fn get_definitions(&self) -> Vec<String>;
+
+    /// Rewrites the include paths with `f`, e.g. to make them relative to another directory
+    fn map_include_paths(&mut self, f: impl Fn(&str) -> String);
+}
+
+impl CompileOptionsExtensions for CompileOptions {
+    fn merge(&mut self, other: &CompileOptions) {
+        extend_unique(
+            &mut self.include_paths,
+            other.include_paths.iter().flatten(),
+        );
+        extend_unique(
+            &mut self.system_includes,
+            other.system_includes.iter().flatten(),
+        );
+        extend(&mut self.cpp_flags, other.cpp_flags.iter().flatten());
+        extend(&mut self.c_flags, other.c_flags.iter().flatten());
+        if let Some(defines) = &other.defines {
+            self.defines
+                .get_or_insert_with(Default::default)
+                .extend(defines.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        extend(&mut self.link_flags, other.link_flags.iter().flatten());
+        extend_unique(
+            &mut self.link_libraries,
+            other.link_libraries.iter().flatten(),
+        );
+    }
+
+    fn replace(&mut self, other: &CompileOptions) {
+        replace(&mut self.include_paths, &other.include_paths);
+        replace(&mut self.system_includes, &other.system_includes);
+        replace(&mut self.cpp_flags, &other.cpp_flags);
+        replace(&mut self.c_flags, &other.c_flags);
+        replace(&mut self.defines, &other.defines);
+        replace(&mut self.link_flags, &other.link_flags);
+        replace(&mut self.link_libraries, &other.link_libraries);
+    }
+
+    fn get_definitions(&self) -> Vec<String> {
+        self.defines
+            .iter()
+            .flatten()
+            .map(|(name, value)| match value {
+                Some(value) => format!("{name}={value}"),
+                None => name.clone(),
+            })
+            .collect()
+    }
+
+    fn map_include_paths(&mut self, f: impl Fn(&str) -> String) {
+        for path in self
+            .include_paths
+            .iter_mut()
+            .chain(self.system_includes.iter_mut())
+            .flatten()
+        {
+            *path = f(path);
+        }
+    }
+}
+
+fn replace<T: Clone>(target: &mut Option<T>, value: &Option<T>) {
+    if value.is_some() {
+        target.clone_from(value);
+    }
+}
+
+fn extend<'a>(target: &mut Option<Vec<String>>, values: impl Iterator<Item = &'a String>) {
+    let mut values = values.peekable();
+    if values.peek().is_none() {
+        return;
+    }
+
+    target.get_or_insert_with(Vec::new).extend(values.cloned());
+}
+
+fn extend_unique<'a>(target: &mut Option<Vec<String>>, values: impl Iterator<Item = &'a String>) {
+    let mut values = values.peekable();
+    if values.peek().is_none() {
+        return;
+    }
+
+    let target = target.get_or_insert_with(Vec::new);
+    for value in values {
+        if !target.contains(value) {
+            target.push(value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Option<Vec<String>> {
+        Some(values.iter().map(|v| v.to_string()).collect())
+    }
+
+    #[test]
+    fn merges_defines_and_link_options() {
+        let mut compile_options = CompileOptions {
+            defines: Some(
+                [
+                    ("VERSION".to_string(), Some("1".to_string())),
+                    ("DEBUG".to_string(), None),
+                ]
+                .into(),
+            ),
+            link_flags: strings(&["-Wl,--gc-sections"]),
+            link_libraries: strings(&["log"]),
+            ..Default::default()
+        };
+        compile_options.merge(&CompileOptions {
+            defines: Some([("VERSION".to_string(), Some("2".to_string()))].into()),
+            link_flags: strings(&["-Wl,--gc-sections", "-Wl,-z,now"]),
+            link_libraries: strings(&["android", "log"]),
+            ..Default::default()
+        });
+
+        assert_eq!(compile_options.get_definitions(), ["DEBUG", "VERSION=2"]);
+        assert_eq!(
+            compile_options.link_flags,
+            strings(&["-Wl,--gc-sections", "-Wl,--gc-sections", "-Wl,-z,now"])
+        );
+        assert_eq!(compile_options.link_libraries, strings(&["log", "android"]));
+    }
+
+    #[test]
+    fn replaces_only_specified_options() {
+        let mut compile_options = CompileOptions {
+            defines: Some([("DEBUG".to_string(), None)].into()),
+            link_flags: strings(&["-Wl,--gc-sections"]),
+            link_libraries: strings(&["log"]),
+            ..Default::default()
+        };
+        compile_options.replace(&CompileOptions {
+            defines: Some([("NDEBUG".to_string(), None)].into()),
+            link_libraries: strings(&[]),
+            ..Default::default()
+        });
+
+        assert_eq!(compile_options.get_definitions(), ["NDEBUG"]);
+        assert_eq!(compile_options.link_flags, strings(&["-Wl,--gc-sections"]));
+        assert_eq!(compile_options.link_libraries, strings(&[]));
+    }
+}
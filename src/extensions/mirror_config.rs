@@ -0,0 +1,324 @@
+use crate::{
+    download::{is_url, DownloadAction, SourceLocation},
+    models::{
+        dependency::SharedPackageConfig,
+        extra::{AdditionalPackageMetadata, PackageDependencyModifier},
+        mirror::MirrorConfig,
+        package::PackageConfig,
+        source::DependencySource,
+    },
+};
+
+pub trait MirrorConfigExtensions {
+    /// The urls to try for a url in order: its mirrors, then the url itself unless a rule excludes it.
+    /// The url itself is kept if no rule gives a mirror for it, so the result is never empty.
+    fn get_urls(&self, url: &str) -> Vec<String>;
+
+    /// The url to try first for a url, the first of [`MirrorConfigExtensions::get_urls`]
+    fn rewrite(&self, url: &str) -> String {
+        self.get_urls(url)
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| url.to_string())
+    }
+
+    /// The urls a url may have been rewritten from, to find what was stored under the original url
+    fn get_original_urls(&self, url: &str) -> Vec<String>;
+
+    /// Rewrites the links of published metadata
+    fn apply_to_metadata(&self, metadata: &mut AdditionalPackageMetadata);
+
+    /// Rewrites the url, links and dependency sources of a package, including extra files given as urls
+    fn apply_to_package(&self, config: &mut PackageConfig);
+
+    /// Rewrites the package and every restored dependency, including their recorded sources
+    fn apply_to_shared(&self, shared: &mut SharedPackageConfig);
+
+    /// Rewrites the urls of a download plan, keeping the remaining urls as fallbacks.
+    /// [`crate::download::get_download_plan`] already applies this, followed by the auth of the new urls.
+    fn apply_to_download_plan(&self, actions: &mut [DownloadAction]);
+}
+
+impl MirrorConfigExtensions for MirrorConfig {
+    fn get_urls(&self, url: &str) -> Vec<String> {
+        let mut urls: Vec<String> = vec![];
+        let mut keep_original = true;
+
+        for rule in &self.rules {
+            let Some(rest) = url.strip_prefix(&rule.prefix) else {
+                continue;
+            };
+
+            keep_original &= rule.fallback_to_original.unwrap_or(true);
+            for mirror in &rule.mirrors {
+                let mirrored = format!("{mirror}{rest}");
+                if !urls.contains(&mirrored) {
+                    urls.push(mirrored);
+                }
+            }
+        }
+
+        // without a mirror, excluding the original would leave nothing to download
+        if (keep_original || urls.is_empty()) && !urls.iter().any(|u| u == url) {
+            urls.push(url.to_string());
+        }
+        urls
+    }
+
+    fn get_original_urls(&self, url: &str) -> Vec<String> {
+        let mut urls: Vec<String> = vec![];
+        for rule in &self.rules {
+            for mirror in &rule.mirrors {
+                let Some(rest) = url.strip_prefix(mirror.as_str()) else {
+                    continue;
+                };
+
+                let original = format!("{}{rest}", rule.prefix);
+                if !urls.contains(&original) {
+                    urls.push(original);
+                }
+            }
+        }
+        urls
+    }
+
+    fn apply_to_metadata(&self, metadata: &mut AdditionalPackageMetadata) {
+        let links = [
+            &mut metadata.so_link,
+            &mut metadata.debug_so_link,
+            &mut metadata.static_link,
+            &mut metadata.mod_link,
+        ];
+        for link in links.into_iter().flatten() {
+            *link = self.rewrite(link);
+        }
+
+        for profile in metadata.profiles.iter_mut().flat_map(|p| p.values_mut()) {
+            let links = [
+                &mut profile.so_link,
+                &mut profile.debug_so_link,
+                &mut profile.static_link,
+            ];
+            for link in links.into_iter().flatten() {
+                *link = self.rewrite(link);
+            }
+        }
+    }
+
+    fn apply_to_package(&self, config: &mut PackageConfig) {
+        let info = &mut config.info;
+        if let Some(url) = &mut info.url {
+            *url = self.rewrite(url);
+        }
+        self.apply_to_metadata(&mut info.additional_data);
+
+        for dependency in &mut config.dependencies {
+            apply_to_modifier(self, &mut dependency.additional_data);
+            if let Some(source) = &mut dependency.source {
+                apply_to_source(self, source);
+            }
+        }
+        for dependency_override in &mut config.overrides {
+            if let Some(source) = &mut dependency_override.source {
+                apply_to_source(self, source);
+            }
+        }
+    }
+
+    fn apply_to_shared(&self, shared: &mut SharedPackageConfig) {
+        self.apply_to_package(&mut shared.config);
+
+        for dependency in &mut shared.restored_dependencies {
+            #[allow(deprecated)]
+            self.apply_to_metadata(&mut dependency.dependency.additional_data);
+            if let Some(source) = &mut dependency.source {
+                apply_to_source(self, source);
+            }
+        }
+    }
+
+    fn apply_to_download_plan(&self, actions: &mut [DownloadAction]) {
+        for action in actions {
+            let (url, fallback_urls) = match action {
+                DownloadAction::FetchSource {
+                    source: SourceLocation::Git { url, .. } | SourceLocation::Zip { url },
+                    fallback_urls,
+                    ..
+                }
+                | DownloadAction::DownloadArtifact {
+                    url, fallback_urls, ..
+                }
+                | DownloadAction::DownloadFile {
+                    url, fallback_urls, ..
+                } => (url, fallback_urls),
+                DownloadAction::FetchSource { .. } | DownloadAction::Copy { .. } => continue,
+            };
+
+            let mut urls = self.get_urls(url).into_iter();
+            if let Some(first) = urls.next() {
+                *url = first;
+            }
+            *fallback_urls = urls.collect();
+        }
+    }
+}
+
+fn apply_to_modifier(mirrors: &MirrorConfig, modifier: &mut PackageDependencyModifier) {
+    for file in modifier.extra_files.iter_mut().flatten() {
+        if is_url(file) {
+            *file = mirrors.rewrite(file);
+        }
+    }
+}
+
+fn apply_to_source(mirrors: &MirrorConfig, source: &mut DependencySource) {
+    match source {
+        DependencySource::Git { url, .. } | DependencySource::Url { url, .. } => {
+            *url = mirrors.rewrite(url);
+        }
+        DependencySource::Registry { .. } | DependencySource::Path { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::VersionReq;
+
+    use super::*;
+    use crate::models::{mirror::MirrorRule, package::PackageDependency};
+
+    fn mirrors() -> MirrorConfig {
+        MirrorConfig {
+            rules: vec![
+                MirrorRule {
+                    prefix: "https://github.com/".to_string(),
+                    mirrors: vec![
+                        "https://mirror.test/github/".to_string(),
+                        "https://backup.test/github/".to_string(),
+                    ],
+                    fallback_to_original: None,
+                },
+                MirrorRule {
+                    prefix: "https://github.com/private/".to_string(),
+                    mirrors: vec!["https://internal.test/".to_string()],
+                    fallback_to_original: Some(false),
+                },
+                MirrorRule {
+                    prefix: "https://files.test/".to_string(),
+                    mirrors: vec![],
+                    fallback_to_original: Some(false),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn gets_mirrors_in_order() {
+        let mirrors = mirrors();
+
+        assert_eq!(
+            mirrors.get_urls("https://github.com/a/b.so"),
+            [
+                "https://mirror.test/github/a/b.so",
+                "https://backup.test/github/a/b.so",
+                "https://github.com/a/b.so"
+            ]
+        );
+        assert_eq!(
+            mirrors.get_urls("https://github.com/private/b.so"),
+            [
+                "https://mirror.test/github/private/b.so",
+                "https://backup.test/github/private/b.so",
+                "https://internal.test/b.so"
+            ]
+        );
+        // without a mirror the original is kept, even if excluded
+        assert_eq!(
+            mirrors.get_urls("https://files.test/b.so"),
+            ["https://files.test/b.so"]
+        );
+        assert_eq!(
+            mirrors.get_urls("https://other.test/b.so"),
+            ["https://other.test/b.so"]
+        );
+
+        assert_eq!(
+            mirrors.get_original_urls("https://internal.test/b.so"),
+            ["https://github.com/private/b.so"]
+        );
+        assert_eq!(
+            mirrors.get_original_urls("https://other.test/b.so"),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn rewrites_links_and_sources_of_packages() {
+        let mut config = PackageConfig::default();
+        config.info.url = Some("https://github.com/a/package".to_string());
+        config.info.additional_data = AdditionalPackageMetadata {
+            so_link: Some("https://github.com/a/lib.so".to_string()),
+            debug_so_link: Some("https://github.com/a/debug.so".to_string()),
+            static_link: Some("https://github.com/a/lib.a".to_string()),
+            mod_link: Some("https://github.com/a/package.qmod".to_string()),
+            ..Default::default()
+        };
+        config.dependencies = vec![PackageDependency {
+            id: "dep".to_string(),
+            version_range: VersionReq::STAR,
+            source: Some(DependencySource::Git {
+                url: "https://github.com/a/dep.git".to_string(),
+                reference: None,
+                sub_folder: None,
+            }),
+            additional_data: PackageDependencyModifier {
+                extra_files: Some(vec![
+                    "https://github.com/a/extra.txt".to_string(),
+                    "assets/extra.txt".to_string(),
+                ]),
+                ..Default::default()
+            },
+        }];
+
+        mirrors().apply_to_package(&mut config);
+
+        let info = &config.info;
+        assert_eq!(
+            info.url.as_deref(),
+            Some("https://mirror.test/github/a/package")
+        );
+        let metadata = &info.additional_data;
+        assert_eq!(
+            [
+                &metadata.so_link,
+                &metadata.debug_so_link,
+                &metadata.static_link,
+                &metadata.mod_link
+            ]
+            .map(|link| link.as_deref().unwrap()),
+            [
+                "https://mirror.test/github/a/lib.so",
+                "https://mirror.test/github/a/debug.so",
+                "https://mirror.test/github/a/lib.a",
+                "https://mirror.test/github/a/package.qmod"
+            ]
+        );
+
+        let dependency = &config.dependencies[0];
+        assert_eq!(
+            dependency.source,
+            Some(DependencySource::Git {
+                url: "https://mirror.test/github/a/dep.git".to_string(),
+                reference: None,
+                sub_folder: None,
+            })
+        );
+        assert_eq!(
+            dependency.additional_data.extra_files,
+            Some(vec![
+                "https://mirror.test/github/a/extra.txt".to_string(),
+                "assets/extra.txt".to_string(),
+            ])
+        );
+    }
+}
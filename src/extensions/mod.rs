@@ -1,2 +1,10 @@
 pub mod package_metadata;
+pub mod additional_metadata;
+pub mod auth_config;
+pub mod compile_options;
+pub mod mirror_config;
+pub mod package_config;
+pub mod package_dependency;
+pub mod shared_dependency;
+pub mod shared_package_config;
 pub mod workspace;
\ No newline at end of file
@@ -0,0 +1,50 @@
+use crate::models::{package::PackageConfig, toolchain::Toolchain};
+
+pub const PACKAGE_FILE_NAME: &str = "qpm.json";
+
+pub trait PackageConfigExtensions {
+    /// The toolchain of the package itself, without any restored dependencies
+    fn get_toolchain(&self) -> Toolchain;
+}
+
+impl PackageConfigExtensions for PackageConfig {
+    fn get_toolchain(&self) -> Toolchain {
+        let compile_options = self
+            .info
+            .additional_data
+            .compile_options
+            .clone()
+            .unwrap_or_default();
+
+        let mut include_paths = vec![
+            self.shared_dir.clone(),
+            self.dependencies_dir.join("includes"),
+        ];
+        include_paths.extend(
+            compile_options
+                .include_paths
+                .unwrap_or_default()
+                .into_iter()
+                .map(Into::into),
+        );
+
+        Toolchain {
+            include_paths,
+            system_includes: compile_options
+                .system_includes
+                .unwrap_or_default()
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            cpp_flags: compile_options.cpp_flags.unwrap_or_default(),
+            c_flags: compile_options.c_flags.unwrap_or_default(),
+            defines: compile_options.defines.unwrap_or_default(),
+            link_flags: compile_options.link_flags.unwrap_or_default(),
+            system_libraries: compile_options.link_libraries.unwrap_or_default(),
+            linked_libraries: vec![],
+            ndk: self.workspace.ndk.clone(),
+            extern_dir: self.dependencies_dir.clone(),
+            shared_dir: self.shared_dir.clone(),
+        }
+    }
+}
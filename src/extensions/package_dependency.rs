@@ -0,0 +1,14 @@
+use crate::models::{package::PackageDependency, source::DependencySource};
+
+pub trait PackageDependencyExtensions {
+    /// Where the dependency is restored from, falling back to the legacy localPath field
+    fn get_source(&self) -> DependencySource;
+}
+
+impl PackageDependencyExtensions for PackageDependency {
+    fn get_source(&self) -> DependencySource {
+        self.source
+            .clone()
+            .unwrap_or_else(|| DependencySource::from_legacy_modifier(&self.additional_data))
+    }
+}
@@ -61,6 +61,14 @@ impl PackageMetadataExtensions for PackageMetadata {
 
 impl PackageMetadataExtensions for SharedDependency {
     fn get_so_name(&self) -> PathBuf {
+        self.get_so_name2()
+    }
+    fn get_static_name(&self) -> PathBuf {
+        self.get_static_name2()
+    }
+
+    fn get_so_name2(&self) -> PathBuf {
+        #[allow(deprecated)]
         self.dependency
             .additional_data
             .override_so_name
@@ -74,7 +82,9 @@ impl PackageMetadataExtensions for SharedDependency {
             })
             .into()
     }
-    fn get_static_name(&self) -> PathBuf {
+
+    fn get_static_name2(&self) -> PathBuf {
+        #[allow(deprecated)]
         self.dependency
             .additional_data
             .override_static_name
@@ -88,12 +98,4 @@ impl PackageMetadataExtensions for SharedDependency {
             })
             .into()
     }
-
-    fn get_so_name2(&self) -> PathBuf {
-        todo!()
-    }
-
-    fn get_static_name2(&self) -> PathBuf {
-        todo!()
-    }
 }
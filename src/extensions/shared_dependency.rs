@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use crate::{
+    integrity::{digest_eq, sha256_bytes, sha256_file, IntegrityError},
+    models::{artifact::ArtifactKind, dependency::SharedDependency},
+};
+
+use super::additional_metadata::AdditionalPackageMetadataExtensions;
+
+pub trait SharedDependencyExtensions {
+    /// The digests an artifact of this dependency must match: the one recorded on a previous restore
+    /// and the one published with the package
+    fn get_expected_sha256(&self, kind: ArtifactKind) -> Vec<&String>;
+
+    /// Checks a downloaded artifact against its expected digests, returning its digest
+    fn verify_artifact(&self, kind: ArtifactKind, data: &[u8]) -> Result<String, IntegrityError>;
+
+    /// Checks a downloaded artifact file against its expected digests, returning its digest
+    fn verify_artifact_file(
+        &self,
+        kind: ArtifactKind,
+        path: &Path,
+    ) -> Result<String, IntegrityError>;
+
+    /// Verifies a downloaded artifact and records its digest, so later restores must match it
+    fn record_artifact(&mut self, kind: ArtifactKind, data: &[u8]) -> Result<(), IntegrityError> {
+        let sha256 = self.verify_artifact(kind, data)?;
+        self.record_checksum(kind, sha256);
+        Ok(())
+    }
+
+    fn record_checksum(&mut self, kind: ArtifactKind, sha256: String);
+}
+
+impl SharedDependencyExtensions for SharedDependency {
+    fn get_expected_sha256(&self, kind: ArtifactKind) -> Vec<&String> {
+        #[allow(deprecated)]
+        let published = self.dependency.additional_data.get_sha256(kind);
+
+        self.checksums
+            .get(&kind)
+            .into_iter()
+            .chain(published)
+            .collect()
+    }
+
+    fn verify_artifact(&self, kind: ArtifactKind, data: &[u8]) -> Result<String, IntegrityError> {
+        let actual = sha256_bytes(data);
+        check(self, kind, actual)
+    }
+
+    fn verify_artifact_file(
+        &self,
+        kind: ArtifactKind,
+        path: &Path,
+    ) -> Result<String, IntegrityError> {
+        let actual = sha256_file(path)?;
+        check(self, kind, actual)
+    }
+
+    fn record_checksum(&mut self, kind: ArtifactKind, sha256: String) {
+        self.checksums.insert(kind, sha256);
+    }
+}
+
+fn check(
+    dependency: &SharedDependency,
+    kind: ArtifactKind,
+    actual: String,
+) -> Result<String, IntegrityError> {
+    let mismatch = dependency
+        .get_expected_sha256(kind)
+        .into_iter()
+        .find(|expected| !digest_eq(expected, &actual));
+
+    if let Some(expected) = mismatch {
+        return Err(IntegrityError::Mismatch {
+            id: dependency.dependency.id.clone(),
+            version: dependency.version.clone(),
+            kind,
+            expected: expected.clone(),
+            actual,
+        });
+    }
+
+    Ok(actual)
+}
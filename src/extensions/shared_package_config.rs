@@ -0,0 +1,667 @@
+use std::{collections::BTreeSet, path::PathBuf};
+
+use crate::models::{
+    dependency::{SharedDependency, SharedPackageConfig},
+    extra::{CompileOptions, DependencyLibType, PackageDependencyModifier},
+    package::PackageDependency,
+    toolchain::{Toolchain, ToolchainLibrary},
+};
+
+use super::{
+    additional_metadata::AdditionalPackageMetadataExtensions,
+    compile_options::CompileOptionsExtensions, package_config::PackageConfigExtensions,
+    package_metadata::PackageMetadataExtensions,
+};
+
+pub const SHARED_PACKAGE_FILE_NAME: &str = "qpm.shared.json";
+
+pub trait SharedPackageConfigExtensions {
+    /// How the root package declares a restored dependency, if it is a direct dependency.
+    /// Renamed packages are found by the id they were declared with.
+    fn get_declared_dependency(&self, dependency: &SharedDependency) -> Option<&PackageDependency>;
+
+    /// The modifier the root package declares for a restored dependency, if it is a direct dependency
+    fn get_dependency_modifier(
+        &self,
+        dependency: &SharedDependency,
+    ) -> Option<&PackageDependencyModifier>;
+
+    /// How a restored dependency should be linked, preferring the lib type declared by the root package
+    fn get_dependency_lib_type(&self, dependency: &SharedDependency) -> DependencyLibType;
+
+    /// The directory the headers of a restored dependency are stored in
+    fn get_dependency_include_dir(&self, dependency: &SharedDependency) -> PathBuf;
+
+    /// The compile options a restored dependency publishes combined with the override declared by this package,
+    /// with include paths rewritten relative to the dependencies directory
+    fn get_dependency_compile_options(
+        &self,
+        dependency: &SharedDependency,
+    ) -> Option<CompileOptions>;
+
+    /// The compile options of all restored dependencies, required to build this package.
+    /// This includes private dependencies.
+    fn get_compile_options(&self) -> CompileOptions;
+
+    /// The compile options of the restored dependencies that are exposed to packages depending on this one.
+    /// Dependencies only reachable through a dependency marked private by this package are left out.
+    /// Lockfiles that don't record which packages require a dependency only leave out the private dependencies.
+    fn get_public_compile_options(&self) -> CompileOptions;
+
+    /// The toolchain of the package including all restored dependencies
+    fn get_toolchain(&self) -> Toolchain;
+
+    /// The effective configuration when building with the given profile,
+    /// applied to this package and all restored dependencies.
+    /// Digests recorded for links the profile replaces are dropped, the digests published for the profile apply instead.
+    fn with_profile(&self, profile: &str) -> SharedPackageConfig;
+}
+
+impl SharedPackageConfigExtensions for SharedPackageConfig {
+    fn get_declared_dependency(&self, dependency: &SharedDependency) -> Option<&PackageDependency> {
+        let id = dependency
+            .declared_id
+            .as_ref()
+            .unwrap_or(&dependency.dependency.id);
+        self.config.dependencies.iter().find(|d| &d.id == id)
+    }
+
+    fn get_dependency_modifier(
+        &self,
+        dependency: &SharedDependency,
+    ) -> Option<&PackageDependencyModifier> {
+        self.get_declared_dependency(dependency)
+            .map(|d| &d.additional_data)
+    }
+
+    fn get_dependency_lib_type(&self, dependency: &SharedDependency) -> DependencyLibType {
+        #[allow(deprecated)]
+        let metadata = &dependency.dependency.additional_data;
+
+        self.get_dependency_modifier(dependency)
+            .and_then(|m| m.lib_type.clone())
+            .unwrap_or_else(|| metadata.get_lib_type())
+    }
+
+    fn get_dependency_include_dir(&self, dependency: &SharedDependency) -> PathBuf {
+        self.config
+            .dependencies_dir
+            .join("includes")
+            .join(&dependency.dependency.id)
+    }
+
+    fn get_dependency_compile_options(
+        &self,
+        dependency: &SharedDependency,
+    ) -> Option<CompileOptions> {
+        #[allow(deprecated)]
+        let published = dependency
+            .dependency
+            .additional_data
+            .compile_options
+            .as_ref();
+        let modifier = self.get_dependency_modifier(dependency);
+        let override_options = modifier.and_then(|m| m.compile_options.as_ref());
+
+        let mut compile_options = match (published, override_options) {
+            (None, None) => return None,
+            (Some(published), None) => published.clone(),
+            (published, Some(override_options)) => {
+                let mut compile_options = published.cloned().unwrap_or_default();
+                let mode = modifier
+                    .and_then(|m| m.compile_options_mode)
+                    .unwrap_or_default();
+                compile_options.apply(override_options, mode);
+                compile_options
+            }
+        };
+
+        let include_dir = self.get_dependency_include_dir(dependency);
+        compile_options.map_include_paths(|p| include_dir.join(p).to_string_lossy().into_owned());
+
+        Some(compile_options)
+    }
+
+    fn get_compile_options(&self) -> CompileOptions {
+        let mut compile_options = CompileOptions::default();
+        for dependency in &self.restored_dependencies {
+            if let Some(dependency_options) = self.get_dependency_compile_options(dependency) {
+                compile_options.merge(&dependency_options);
+            }
+        }
+
+        compile_options
+    }
+
+    fn get_public_compile_options(&self) -> CompileOptions {
+        let root = self.config.info.id.as_str();
+        // private dependencies of dependencies are not restored, so only edges from this package can be private
+        let is_public_edge = |dependency: &SharedDependency, required_by: &str| {
+            required_by != root
+                || !self
+                    .get_dependency_modifier(dependency)
+                    .and_then(|m| m.is_private)
+                    .unwrap_or(false)
+        };
+
+        // walk the dependencies reachable from this package through public edges until no more are found
+        let mut public: BTreeSet<&str> = BTreeSet::new();
+        loop {
+            let found = public.len();
+            for dependency in &self.restored_dependencies {
+                let id = dependency.dependency.id.as_str();
+                if public.contains(id) {
+                    continue;
+                }
+
+                let is_reachable = match dependency.required_by.is_empty() {
+                    true => is_public_edge(dependency, root),
+                    false => dependency.required_by.iter().any(|required_by| {
+                        (required_by == root || public.contains(required_by.as_str()))
+                            && is_public_edge(dependency, required_by)
+                    }),
+                };
+                if is_reachable {
+                    public.insert(id);
+                }
+            }
+
+            if public.len() == found {
+                break;
+            }
+        }
+
+        let mut compile_options = CompileOptions::default();
+        for dependency in &self.restored_dependencies {
+            if !public.contains(dependency.dependency.id.as_str()) {
+                continue;
+            }
+
+            if let Some(dependency_options) = self.get_dependency_compile_options(dependency) {
+                compile_options.merge(&dependency_options);
+            }
+        }
+
+        compile_options
+    }
+
+    fn get_toolchain(&self) -> Toolchain {
+        let mut toolchain = self.config.get_toolchain();
+        let compile_options = self.get_compile_options();
+
+        for path in compile_options.include_paths.into_iter().flatten() {
+            let path = PathBuf::from(path);
+            if !toolchain.include_paths.contains(&path) {
+                toolchain.include_paths.push(path);
+            }
+        }
+        for path in compile_options.system_includes.into_iter().flatten() {
+            let path = PathBuf::from(path);
+            if !toolchain.system_includes.contains(&path) {
+                toolchain.system_includes.push(path);
+            }
+        }
+        toolchain
+            .cpp_flags
+            .extend(compile_options.cpp_flags.into_iter().flatten());
+        toolchain
+            .c_flags
+            .extend(compile_options.c_flags.into_iter().flatten());
+        // defines of the package itself take precedence over those of its dependencies
+        for (name, value) in compile_options.defines.into_iter().flatten() {
+            toolchain.defines.entry(name).or_insert(value);
+        }
+        toolchain
+            .link_flags
+            .extend(compile_options.link_flags.into_iter().flatten());
+        for library in compile_options.link_libraries.into_iter().flatten() {
+            if !toolchain.system_libraries.contains(&library) {
+                toolchain.system_libraries.push(library);
+            }
+        }
+
+        let libs_dir = self.config.dependencies_dir.join("libs");
+        for dependency in &self.restored_dependencies {
+            let lib_type = self.get_dependency_lib_type(dependency);
+            let name = match lib_type {
+                DependencyLibType::Shared => dependency.get_so_name2(),
+                DependencyLibType::Static => dependency.get_static_name2(),
+                DependencyLibType::HeaderOnly => continue,
+            };
+
+            toolchain.linked_libraries.push(ToolchainLibrary {
+                id: dependency.dependency.id.clone(),
+                lib_type,
+                path: libs_dir.join(name),
+            });
+        }
+
+        toolchain
+    }
+
+    fn with_profile(&self, profile: &str) -> SharedPackageConfig {
+        let mut shared = self.clone();
+
+        let info = &mut shared.config.info;
+        info.additional_data = info.additional_data.with_profile(profile);
+
+        for dependency in &mut shared.restored_dependencies {
+            #[allow(deprecated)]
+            let metadata = &mut dependency.dependency.additional_data;
+            let profile_metadata = metadata.with_profile(profile);
+
+            // recorded digests belong to the link they were downloaded from
+            dependency
+                .checksums
+                .retain(|kind, _| metadata.get_link(*kind) == profile_metadata.get_link(*kind));
+            *metadata = profile_metadata;
+        }
+
+        shared
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::{Version, VersionReq};
+
+    use super::*;
+    use crate::{
+        extensions::shared_dependency::SharedDependencyExtensions,
+        integrity::sha256_bytes,
+        models::{
+            artifact::ArtifactKind,
+            dependency::Dependency,
+            extra::{AdditionalPackageMetadata, CompileOptionsMergeMode},
+            package::PackageConfig,
+        },
+    };
+
+    fn declared(id: &str, additional_data: PackageDependencyModifier) -> PackageDependency {
+        PackageDependency {
+            id: id.to_string(),
+            version_range: VersionReq::STAR,
+            source: None,
+            additional_data,
+        }
+    }
+
+    fn with_compile_options(compile_options: CompileOptions) -> AdditionalPackageMetadata {
+        AdditionalPackageMetadata {
+            headers_only: Some(true),
+            compile_options: Some(compile_options),
+            ..Default::default()
+        }
+    }
+
+    fn includes(paths: &[&str]) -> Option<Vec<String>> {
+        Some(paths.iter().map(|p| p.to_string()).collect())
+    }
+
+    fn restored(id: &str, additional_data: AdditionalPackageMetadata) -> SharedDependency {
+        #[allow(deprecated)]
+        SharedDependency {
+            dependency: Dependency {
+                id: id.to_string(),
+                version_range: VersionReq::STAR,
+                additional_data,
+            },
+            version: Version::new(1, 0, 0),
+            checksums: Default::default(),
+            publisher_key: None,
+            source: None,
+            required_by: vec![],
+            declared_id: None,
+            applied_override: None,
+        }
+    }
+
+    fn shared(restored_dependencies: Vec<SharedDependency>) -> SharedPackageConfig {
+        SharedPackageConfig {
+            config: PackageConfig {
+                shared_dir: "shared".into(),
+                dependencies_dir: "extern".into(),
+                ..Default::default()
+            },
+            restored_dependencies,
+        }
+    }
+
+    #[test]
+    fn generates_toolchain_from_restored_dependencies() {
+        let mut shared = shared(vec![
+            restored(
+                "shared-dep",
+                AdditionalPackageMetadata {
+                    so_link: Some("https://files.test/shared-dep.so".to_string()),
+                    ..Default::default()
+                },
+            ),
+            restored(
+                "static-dep",
+                AdditionalPackageMetadata {
+                    static_link: Some("https://files.test/static-dep.a".to_string()),
+                    ..Default::default()
+                },
+            ),
+            restored(
+                "headers-dep",
+                AdditionalPackageMetadata {
+                    headers_only: Some(true),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        shared.config.workspace.ndk = Some(VersionReq::parse("^27").unwrap());
+        shared.config.info.additional_data.compile_options = Some(CompileOptions {
+            include_paths: Some(vec!["include".to_string()]),
+            cpp_flags: Some(vec!["-Wall".to_string()]),
+            ..Default::default()
+        });
+
+        let toolchain = shared.get_toolchain();
+        assert_eq!(
+            toolchain.include_paths,
+            [
+                PathBuf::from("shared"),
+                PathBuf::from("extern/includes"),
+                PathBuf::from("include")
+            ]
+        );
+        assert_eq!(toolchain.cpp_flags, ["-Wall"]);
+        assert_eq!(toolchain.ndk, shared.config.workspace.ndk);
+        assert_eq!(toolchain.extern_dir, PathBuf::from("extern"));
+        assert_eq!(toolchain.shared_dir, PathBuf::from("shared"));
+        assert_eq!(
+            toolchain.linked_libraries,
+            [
+                ToolchainLibrary {
+                    id: "shared-dep".to_string(),
+                    lib_type: DependencyLibType::Shared,
+                    path: PathBuf::from("extern/libs/libshared-dep_1_0_0.so"),
+                },
+                ToolchainLibrary {
+                    id: "static-dep".to_string(),
+                    lib_type: DependencyLibType::Static,
+                    path: PathBuf::from("extern/libs/libstatic-dep_1_0_0.a"),
+                },
+            ]
+        );
+
+        // the ndk range is optional
+        let toolchain = Toolchain {
+            ndk: None,
+            ..toolchain
+        };
+        let json = serde_json::to_value(&toolchain).unwrap();
+        assert!(json.get("ndk").is_none());
+        assert_eq!(
+            serde_json::from_value::<Toolchain>(json).unwrap(),
+            toolchain
+        );
+    }
+
+    #[test]
+    fn propagates_defines_and_link_options_to_toolchain() {
+        let mut shared = shared(vec![restored(
+            "dep",
+            with_compile_options(CompileOptions {
+                defines: Some(
+                    [
+                        ("LOG_LEVEL".to_string(), Some("0".to_string())),
+                        ("DEP_API".to_string(), None),
+                    ]
+                    .into(),
+                ),
+                link_flags: Some(vec!["-Wl,-z,now".to_string()]),
+                link_libraries: Some(vec!["log".to_string(), "android".to_string()]),
+                ..Default::default()
+            }),
+        )]);
+        shared.config.info.additional_data.compile_options = Some(CompileOptions {
+            defines: Some([("LOG_LEVEL".to_string(), Some("2".to_string()))].into()),
+            link_flags: Some(vec!["-Wl,--gc-sections".to_string()]),
+            link_libraries: Some(vec!["log".to_string()]),
+            ..Default::default()
+        });
+
+        let toolchain = shared.get_toolchain();
+        // defines of the package take precedence over those of its dependencies
+        assert_eq!(
+            toolchain.defines,
+            [
+                ("DEP_API".to_string(), None),
+                ("LOG_LEVEL".to_string(), Some("2".to_string()))
+            ]
+            .into()
+        );
+        assert_eq!(toolchain.link_flags, ["-Wl,--gc-sections", "-Wl,-z,now"]);
+        assert_eq!(toolchain.system_libraries, ["log", "android"]);
+        assert!(toolchain.linked_libraries.is_empty());
+    }
+
+    #[test]
+    fn merges_compile_options_of_restored_dependencies() {
+        let shared = shared(vec![
+            restored(
+                "a",
+                with_compile_options(CompileOptions {
+                    include_paths: includes(&["include", "shared"]),
+                    system_includes: includes(&["system"]),
+                    cpp_flags: Some(vec!["-Wall".to_string()]),
+                    link_libraries: Some(vec!["log".to_string()]),
+                    ..Default::default()
+                }),
+            ),
+            restored(
+                "b",
+                with_compile_options(CompileOptions {
+                    include_paths: includes(&["include", "include"]),
+                    cpp_flags: Some(vec!["-Wall".to_string()]),
+                    link_libraries: Some(vec!["android".to_string(), "log".to_string()]),
+                    ..Default::default()
+                }),
+            ),
+            restored("c", Default::default()),
+        ]);
+
+        let compile_options = shared.get_compile_options();
+        assert_eq!(
+            compile_options.include_paths,
+            includes(&[
+                "extern/includes/a/include",
+                "extern/includes/a/shared",
+                "extern/includes/b/include"
+            ])
+        );
+        assert_eq!(
+            compile_options.system_includes,
+            includes(&["extern/includes/a/system"])
+        );
+        // flags keep their position, so they are not deduplicated
+        assert_eq!(
+            compile_options.cpp_flags,
+            Some(vec!["-Wall".to_string(), "-Wall".to_string()])
+        );
+        assert_eq!(
+            compile_options.link_libraries,
+            Some(vec!["log".to_string(), "android".to_string()])
+        );
+    }
+
+    #[test]
+    fn leaves_private_dependencies_out_of_public_compile_options() {
+        let dependency = |id: &str, required_by: &[&str]| SharedDependency {
+            required_by: required_by.iter().map(|r| r.to_string()).collect(),
+            ..restored(
+                id,
+                with_compile_options(CompileOptions {
+                    include_paths: includes(&["include"]),
+                    ..Default::default()
+                }),
+            )
+        };
+        let mut shared = shared(vec![
+            dependency("private", &["root"]),
+            dependency("public", &["root"]),
+            dependency("private-only", &["private"]),
+            dependency("both", &["private", "public"]),
+        ]);
+        shared.config.info.id = "root".to_string();
+        shared.config.dependencies = vec![
+            declared(
+                "private",
+                PackageDependencyModifier {
+                    is_private: Some(true),
+                    ..Default::default()
+                },
+            ),
+            declared("public", Default::default()),
+        ];
+
+        assert_eq!(
+            shared.get_compile_options().include_paths,
+            includes(&[
+                "extern/includes/private/include",
+                "extern/includes/public/include",
+                "extern/includes/private-only/include",
+                "extern/includes/both/include"
+            ])
+        );
+        assert_eq!(
+            shared.get_public_compile_options().include_paths,
+            includes(&[
+                "extern/includes/public/include",
+                "extern/includes/both/include"
+            ])
+        );
+
+        // without recorded requirements only the private dependency itself is left out
+        for dependency in &mut shared.restored_dependencies {
+            dependency.required_by.clear();
+        }
+        assert_eq!(
+            shared.get_public_compile_options().include_paths,
+            includes(&[
+                "extern/includes/public/include",
+                "extern/includes/private-only/include",
+                "extern/includes/both/include"
+            ])
+        );
+    }
+
+    #[test]
+    fn applies_compile_option_overrides_of_declared_dependencies() {
+        let published = CompileOptions {
+            include_paths: includes(&["include"]),
+            cpp_flags: Some(vec!["-Wall".to_string()]),
+            ..Default::default()
+        };
+        let override_options = CompileOptions {
+            include_paths: includes(&["generated"]),
+            cpp_flags: Some(vec!["-Wno-unused".to_string()]),
+            ..Default::default()
+        };
+        let mut shared = shared(vec![
+            restored("extended", with_compile_options(published.clone())),
+            restored("replaced", with_compile_options(published)),
+            restored("unpublished", Default::default()),
+        ]);
+        let modifier = |mode| PackageDependencyModifier {
+            compile_options: Some(override_options.clone()),
+            compile_options_mode: mode,
+            ..Default::default()
+        };
+        shared.config.dependencies = vec![
+            declared("extended", modifier(None)),
+            declared("replaced", modifier(Some(CompileOptionsMergeMode::Replace))),
+            declared("unpublished", modifier(None)),
+        ];
+
+        let get = |index: usize| {
+            shared
+                .get_dependency_compile_options(&shared.restored_dependencies[index])
+                .unwrap()
+        };
+
+        let extended = get(0);
+        assert_eq!(
+            extended.include_paths,
+            includes(&[
+                "extern/includes/extended/include",
+                "extern/includes/extended/generated"
+            ])
+        );
+        assert_eq!(
+            extended.cpp_flags,
+            Some(vec!["-Wall".to_string(), "-Wno-unused".to_string()])
+        );
+
+        let replaced = get(1);
+        assert_eq!(
+            replaced.include_paths,
+            includes(&["extern/includes/replaced/generated"])
+        );
+        assert_eq!(replaced.cpp_flags, Some(vec!["-Wno-unused".to_string()]));
+
+        let unpublished = get(2);
+        assert_eq!(
+            unpublished.include_paths,
+            includes(&["extern/includes/unpublished/generated"])
+        );
+    }
+
+    #[test]
+    fn replaces_recorded_digests_of_profile_links() {
+        let metadata = AdditionalPackageMetadata {
+            so_link: Some("https://files.test/release.so".to_string()),
+            so_sha256: Some(sha256_bytes(b"release")),
+            debug_so_link: Some("https://files.test/debug.so".to_string()),
+            debug_so_sha256: Some(sha256_bytes(b"debug")),
+            mod_link: Some("https://files.test/dep.qmod".to_string()),
+            ..Default::default()
+        };
+        #[allow(deprecated)]
+        let dependency = SharedDependency {
+            dependency: Dependency {
+                id: "dep".to_string(),
+                version_range: VersionReq::STAR,
+                additional_data: metadata,
+            },
+            version: Version::new(1, 0, 0),
+            checksums: [
+                (ArtifactKind::So, sha256_bytes(b"release")),
+                (ArtifactKind::Mod, sha256_bytes(b"mod")),
+            ]
+            .into(),
+            publisher_key: None,
+            source: None,
+            required_by: vec![],
+            declared_id: None,
+            applied_override: None,
+        };
+        let shared = SharedPackageConfig {
+            config: Default::default(),
+            restored_dependencies: vec![dependency],
+        };
+
+        let release = shared.with_profile("release");
+        let dependency = &release.restored_dependencies[0];
+        assert_eq!(dependency.checksums.len(), 2);
+        dependency
+            .verify_artifact(ArtifactKind::So, b"release")
+            .unwrap();
+
+        // the debug profile links the debug .so in place of the release one
+        let debug = shared.with_profile("debug");
+        let dependency = &debug.restored_dependencies[0];
+        assert!(!dependency.checksums.contains_key(&ArtifactKind::So));
+        assert!(dependency.checksums.contains_key(&ArtifactKind::Mod));
+        dependency
+            .verify_artifact(ArtifactKind::So, b"debug")
+            .unwrap();
+        assert!(dependency
+            .verify_artifact(ArtifactKind::So, b"release")
+            .is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+    credentials::{redact_url, BoxedCredentialProvider, CredentialError, CredentialProvider},
+    extensions::{
+        auth_config::{AuthConfigExtensions, AuthSchemeExtensions},
+        mirror_config::MirrorConfigExtensions,
+    },
+    models::{
+        auth::{AuthConfig, AuthScheme},
+        mirror::MirrorConfig,
+    },
+    registry::http::{HttpRequest, HttpTransport, TransportError},
+};
+
+/// Urls in errors are redacted
+#[derive(Debug, Error)]
+pub enum FetchError {
+    #[error("{url} responded with status {status}")]
+    Http { url: String, status: u16 },
+
+    #[error("{0} can't be fetched by this fetcher")]
+    UnsupportedUrl(String),
+
+    #[error("{0} is not vendored")]
+    NotVendored(String),
+
+    #[error("vendored file of {url} is corrupted, expected sha256 {expected} but found {actual}")]
+    Corrupted {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error(transparent)]
+    Transport(TransportError),
+
+    #[error(transparent)]
+    Credential(#[from] CredentialError),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Retrieves the artifact behind a url, so restores can run against the network or a local copy
+pub trait Fetcher {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError>;
+}
+
+impl<F: Fetcher + ?Sized> Fetcher for &F {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        (**self).fetch(url)
+    }
+}
+
+/// Downloads artifacts over HTTP
+#[derive(Debug)]
+pub struct HttpFetcher<T: HttpTransport> {
+    pub transport: T,
+    /// Asked for the authorization of downloads no auth rule matches, such as artifacts of private packages
+    pub credentials: Option<BoxedCredentialProvider>,
+    /// Authentication of downloads by host
+    pub auth: AuthConfig,
+}
+
+impl<T: HttpTransport> HttpFetcher<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            credentials: None,
+            auth: AuthConfig::default(),
+        }
+    }
+
+    pub fn with_credentials(mut self, credentials: BoxedCredentialProvider) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    pub fn with_auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Downloads a url authenticated by `scheme`, such as the auth recorded in a download plan.
+    /// Falls back to the credential provider without a scheme.
+    pub fn fetch_with_auth(
+        &self,
+        url: &str,
+        scheme: Option<&AuthScheme>,
+    ) -> Result<Vec<u8>, FetchError> {
+        let mut request = HttpRequest::get(url);
+        if let Some(scheme) = scheme {
+            if let Some((name, value)) = scheme.get_header(url)? {
+                request = request.with_secret_header(name, value);
+            }
+        } else if let Some(credentials) = &self.credentials {
+            if let Some(credential) = credentials.get_credential(url)? {
+                request =
+                    request.with_secret_header("Authorization", credential.get_authorization());
+            }
+        }
+
+        let response = self
+            .transport
+            .send(&request)
+            .map_err(FetchError::Transport)?;
+
+        if !response.is_success() {
+            return Err(FetchError::Http {
+                url: redact_url(url).into_owned(),
+                status: response.status,
+            });
+        }
+
+        Ok(response.body)
+    }
+}
+
+impl<T: HttpTransport> Fetcher for HttpFetcher<T> {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        self.fetch_with_auth(url, self.auth.get_scheme(url))
+    }
+}
+
+/// Fetches through the mirrors of a url, returning the first that succeeds or the last error
+#[derive(Clone, Debug)]
+pub struct MirrorFetcher<F: Fetcher> {
+    pub fetcher: F,
+    pub mirrors: MirrorConfig,
+}
+
+impl<F: Fetcher> MirrorFetcher<F> {
+    pub fn new(fetcher: F, mirrors: MirrorConfig) -> Self {
+        Self { fetcher, mirrors }
+    }
+}
+
+impl<F: Fetcher> Fetcher for MirrorFetcher<F> {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        let mut last_error = FetchError::UnsupportedUrl(redact_url(url).into_owned());
+        for candidate in self.mirrors.get_urls(url) {
+            match self.fetcher.fetch(&candidate) {
+                Ok(data) => return Ok(data),
+                Err(e) => last_error = e,
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+/// The `file://` url of an absolute path
+pub fn get_file_url(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    match path.starts_with('/') {
+        true => format!("file://{path}"),
+        false => format!("file:///{path}"),
+    }
+}
+
+/// The path a `file://` url points to
+pub fn get_file_url_path(url: &str) -> Option<PathBuf> {
+    let path = url.strip_prefix("file://")?;
+    // windows paths are written as file:///C:/...
+    let is_drive = path.len() > 2 && path.as_bytes()[2] == b':';
+    Some(PathBuf::from(if is_drive { &path[1..] } else { path }))
+}
+
+/// Reads `file://` urls from disk, such as the links of bundles registered into a local registry
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FileFetcher;
+
+impl Fetcher for FileFetcher {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        let path = get_file_url_path(url)
+            .ok_or_else(|| FetchError::UnsupportedUrl(redact_url(url).into_owned()))?;
+        Ok(fs::read(path)?)
+    }
+}
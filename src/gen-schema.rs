@@ -10,4 +10,24 @@ fn main() {
     let schema_json = schemars::schema_for!(models::package::PackageConfig);
     let schema = serde_json::to_string_pretty(&schema_json).unwrap();
     std::fs::write("qpm.schema.json", schema).expect("Failed to write schema");
+
+    let toolchain_schema_json = schemars::schema_for!(models::toolchain::Toolchain);
+    let toolchain_schema = serde_json::to_string_pretty(&toolchain_schema_json).unwrap();
+    std::fs::write("qpm.toolchain.schema.json", toolchain_schema).expect("Failed to write toolchain schema");
+
+    let listing_schema_json = schemars::schema_for!(models::backend::PackageListing);
+    let listing_schema = serde_json::to_string_pretty(&listing_schema_json).unwrap();
+    std::fs::write("qpm.backend.package.schema.json", listing_schema).expect("Failed to write package listing schema");
+
+    let search_schema_json = schemars::schema_for!(models::backend::PackageSearchResults);
+    let search_schema = serde_json::to_string_pretty(&search_schema_json).unwrap();
+    std::fs::write("qpm.backend.search.schema.json", search_schema).expect("Failed to write search results schema");
+
+    let error_schema_json = schemars::schema_for!(models::backend::RegistryErrorResponse);
+    let error_schema = serde_json::to_string_pretty(&error_schema_json).unwrap();
+    std::fs::write("qpm.backend.error.schema.json", error_schema).expect("Failed to write error schema");
+
+    let user_schema_json = schemars::schema_for!(models::user_config::UserConfig);
+    let user_schema = serde_json::to_string_pretty(&user_schema_json).unwrap();
+    std::fs::write("qpm.settings.schema.json", user_schema).expect("Failed to write settings schema");
 }
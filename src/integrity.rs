@@ -0,0 +1,52 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use semver::Version;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::models::artifact::ArtifactKind;
+
+#[derive(Debug, Error)]
+pub enum IntegrityError {
+    #[error("{kind:?} of {id} {version} does not match its checksum, expected sha256 {expected} but found {actual}")]
+    Mismatch {
+        id: String,
+        version: Version,
+        kind: ArtifactKind,
+        expected: String,
+        actual: String,
+    },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// The lowercase hex SHA-256 digest of `data`
+pub fn sha256_bytes(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// The lowercase hex SHA-256 digest of a file, read in chunks
+pub fn sha256_file(path: impl AsRef<Path>) -> io::Result<String> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Whether two hex digests are equal, ignoring case
+pub fn digest_eq(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
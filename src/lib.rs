@@ -1,2 +1,16 @@
 pub mod models;
-pub mod extensions;
\ No newline at end of file
+pub mod extensions;
+pub mod registry;
+pub mod resolver;
+pub mod cache;
+pub mod integrity;
+pub mod signing;
+pub mod download;
+pub mod git;
+pub mod path_dependency;
+pub mod fetch;
+pub mod vendor;
+pub mod bundle;
+pub mod config;
+pub mod credentials;
+pub mod source_loader;
\ No newline at end of file
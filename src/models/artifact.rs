@@ -0,0 +1,22 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Describes a downloadable artifact of a package.")]
+pub enum ArtifactKind {
+    #[schemars(description = "The shared object file, from soLink")]
+    So,
+
+    #[schemars(description = "The debug shared object file, from debugSoLink")]
+    DebugSo,
+
+    #[schemars(description = "The static library file, from staticLink")]
+    Static,
+
+    #[schemars(description = "The qmod file, from modLink")]
+    Mod,
+
+    #[schemars(description = "The source archive of the package")]
+    Source,
+}
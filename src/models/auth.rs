@@ -0,0 +1,64 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::user_config::TokenReference;
+
+/// Authentication of artifact and source urls by host, such as private GitHub releases
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, Eq, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Authentication sent when downloading artifacts and sources from private hosts.")]
+pub struct AuthConfig {
+    /// Rules are matched in order, the first matching rule is used
+    #[serde(default)]
+    #[schemars(description = "The authentication rules. The first rule matching a URL is used.")]
+    pub rules: Vec<AuthRule>,
+}
+
+/// auth.rules[]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Authenticates URLs of a host.")]
+pub struct AuthRule {
+    /// e.g. `github.com`, `files.example.com:8443` or `*.example.com` for every subdomain
+    #[schemars(description = "The host to authenticate, including its port if it is not the default. *.example.com matches every subdomain of example.com.")]
+    pub host: String,
+
+    /// Restricts the rule to urls whose path starts with these segments, e.g. `/my-org`.
+    /// Paths are percent-decoded and their dot segments resolved before matching
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Only URLs whose path starts with these path segments are authenticated, such as /my-org. Paths are percent-decoded and their dot segments resolved before matching.")]
+    pub path_prefix: Option<String>,
+
+    #[schemars(description = "How requests to the host are authenticated.")]
+    pub scheme: AuthScheme,
+}
+
+/// How a request is authenticated. Secrets are only referenced, never stored
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[schemars(description = "How a request is authenticated. Secrets are read from token references, never stored in the settings.")]
+pub enum AuthScheme {
+    #[schemars(description = "An Authorization: Bearer header.")]
+    Bearer {
+        #[schemars(description = "Where the token is read from.")]
+        token: TokenReference,
+    },
+
+    #[schemars(description = "An Authorization: Basic header.")]
+    Basic {
+        #[schemars(description = "The user name.")]
+        username: String,
+
+        #[schemars(description = "Where the password is read from.")]
+        password: TokenReference,
+    },
+
+    #[schemars(description = "A custom header, such as PRIVATE-TOKEN for GitLab.")]
+    Header {
+        #[schemars(description = "The name of the header.")]
+        name: String,
+
+        #[schemars(description = "Where the value of the header is read from.")]
+        value: TokenReference,
+    },
+}
@@ -1,7 +1,10 @@
+use chrono::{DateTime, Utc};
 use schemars::JsonSchema;
 use semver::Version;
 use serde::{Serialize, Deserialize};
 
+use super::dependency::SharedPackageConfig;
+
 #[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, PartialEq, Eq)]
 #[allow(non_snake_case)]
 #[serde(rename_all = "camelCase")]
@@ -13,3 +16,268 @@ pub struct PackageVersion {
     #[schemars(description = "The version of the package.")]
     pub version: Version,
 }
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "A package and all of its published versions.")]
+pub struct PackageListing {
+    #[schemars(description = "The unique identifier of the package.")]
+    pub id: String,
+
+    #[serde(default)]
+    #[schemars(description = "The users allowed to publish new versions of the package.")]
+    pub owners: Vec<String>,
+
+    #[serde(default)]
+    #[schemars(description = "The total download count of all versions of the package.")]
+    pub downloads: u64,
+
+    #[serde(default)]
+    #[schemars(description = "Whether the package is deprecated.")]
+    pub deprecated: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Why the package is deprecated.")]
+    pub deprecation_reason: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The unique identifier of the package to use instead of this deprecated package.")]
+    pub replacement: Option<String>,
+
+    #[schemars(description = "The published versions of the package.")]
+    pub versions: Vec<PackageVersionListing>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "A published version of a package.")]
+pub struct PackageVersionListing {
+    #[schemars(description = "The version of the package.")]
+    pub version: Version,
+
+    #[schemars(description = "When the version was published.")]
+    pub published_at: DateTime<Utc>,
+
+    #[serde(default)]
+    #[schemars(description = "Whether the version is yanked and should not be used by new restores.")]
+    pub yanked: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Why the version is yanked.")]
+    pub yank_reason: Option<String>,
+
+    #[serde(default)]
+    #[schemars(description = "Whether the version is deprecated.")]
+    pub deprecated: bool,
+
+    #[serde(default)]
+    #[schemars(description = "The download count of the version.")]
+    pub downloads: u64,
+
+    #[schemars(description = "The shared package configuration stored for the version.")]
+    pub config: SharedPackageConfig,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Maps the previous unique identifier of a renamed package to its current one.")]
+pub struct PackageAlias {
+    #[schemars(description = "The previous unique identifier of the package.")]
+    pub alias: String,
+
+    #[schemars(description = "The unique identifier the package was renamed to.")]
+    pub id: String,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Request to yank a published version.")]
+pub struct YankRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Why the version is yanked.")]
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Request to deprecate a package.")]
+pub struct DeprecateRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Why the package is deprecated.")]
+    pub reason: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The unique identifier of the package to use instead.")]
+    pub replacement: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "A page of package search results.")]
+pub struct PackageSearchResults {
+    #[schemars(description = "The packages on this page.")]
+    pub results: Vec<PackageSearchResult>,
+
+    #[schemars(description = "The index of this page, starting at 0.")]
+    pub page: u32,
+
+    #[schemars(description = "The maximum amount of results per page.")]
+    pub per_page: u32,
+
+    #[schemars(description = "The total amount of results across all pages.")]
+    pub total: u64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "A package matching a search.")]
+pub struct PackageSearchResult {
+    #[schemars(description = "The unique identifier of the package.")]
+    pub id: String,
+
+    #[schemars(description = "The name of the package.")]
+    pub name: String,
+
+    #[schemars(description = "The newest version of the package that is not yanked.")]
+    pub latest_version: Version,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The website for the package.")]
+    pub url: Option<String>,
+
+    #[serde(default)]
+    #[schemars(description = "The total download count of all versions of the package.")]
+    pub downloads: u64,
+
+    #[serde(default)]
+    #[schemars(description = "Whether the package is deprecated.")]
+    pub deprecated: bool,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "An error returned by the registry.")]
+pub struct RegistryErrorResponse {
+    #[schemars(description = "The kind of error.")]
+    pub code: RegistryErrorCode,
+
+    #[schemars(description = "A human readable description of the error.")]
+    pub message: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Additional information about the error.")]
+    pub details: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Describes the kind of registry error.")]
+pub enum RegistryErrorCode {
+    #[schemars(description = "The package or version does not exist")]
+    NotFound,
+
+    #[schemars(description = "The version is already published")]
+    AlreadyPublished,
+
+    #[schemars(description = "Authentication is missing or invalid")]
+    Unauthorized,
+
+    #[schemars(description = "The authenticated user may not perform the request")]
+    Forbidden,
+
+    #[schemars(description = "The request is malformed")]
+    InvalidRequest,
+
+    #[schemars(description = "The registry failed to handle the request")]
+    Internal,
+
+    #[serde(other)]
+    #[schemars(description = "An error unknown to this client")]
+    Unknown,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn reads_listings_with_omitted_fields() {
+        let config = serde_json::to_value(SharedPackageConfig {
+            config: Default::default(),
+            restored_dependencies: vec![],
+        })
+        .unwrap();
+        let listing: PackageListing = serde_json::from_value(json!({
+            "id": "core",
+            "versions": [{
+                "version": "1.0.0",
+                "publishedAt": "2024-01-02T03:04:05Z",
+                "config": config,
+            }],
+        }))
+        .unwrap();
+
+        assert!(listing.owners.is_empty());
+        assert_eq!(listing.downloads, 0);
+        assert!(!listing.deprecated);
+        let version = &listing.versions[0];
+        assert_eq!(version.version, Version::new(1, 0, 0));
+        assert_eq!(
+            version.published_at.to_rfc3339(),
+            "2024-01-02T03:04:05+00:00"
+        );
+        assert!(!version.yanked);
+        assert_eq!(version.yank_reason, None);
+
+        let serialized = serde_json::to_value(&listing).unwrap();
+        assert!(serialized.get("deprecationReason").is_none());
+        assert_eq!(
+            serde_json::from_value::<PackageListing>(serialized).unwrap(),
+            listing
+        );
+    }
+
+    #[test]
+    fn reads_search_results() {
+        let results: PackageSearchResults = serde_json::from_value(json!({
+            "results": [{
+                "id": "core",
+                "name": "Core",
+                "latestVersion": "1.2.0",
+                "downloads": 7,
+            }],
+            "page": 1,
+            "perPage": 20,
+            "total": 21,
+        }))
+        .unwrap();
+
+        assert_eq!(results.per_page, 20);
+        let result = &results.results[0];
+        assert_eq!(result.latest_version, Version::new(1, 2, 0));
+        assert_eq!(result.url, None);
+        assert!(!result.deprecated);
+    }
+
+    #[test]
+    fn reads_unknown_error_codes() {
+        let error: RegistryErrorResponse = serde_json::from_value(json!({
+            "code": "alreadyPublished",
+            "message": "core 1.0.0 is already published",
+        }))
+        .unwrap();
+        assert_eq!(error.code, RegistryErrorCode::AlreadyPublished);
+        assert_eq!(error.details, None);
+
+        let error: RegistryErrorResponse = serde_json::from_value(json!({
+            "code": "rateLimited",
+            "message": "slow down",
+            "details": { "retryAfter": 30 },
+        }))
+        .unwrap();
+        assert_eq!(error.code, RegistryErrorCode::Unknown);
+        assert_eq!(error.details.unwrap()["retryAfter"], 30);
+    }
+}
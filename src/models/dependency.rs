@@ -1,8 +1,15 @@
+use std::collections::BTreeMap;
+
 use schemars::JsonSchema;
 use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 
-use super::{extra::AdditionalPackageMetadata, package::PackageConfig};
+use super::{
+    artifact::ArtifactKind,
+    extra::AdditionalPackageMetadata,
+    package::{DependencyOverride, PackageConfig},
+    source::DependencySource,
+};
 
 use crate::models::version_req::make_version_req_schema;
 
@@ -32,6 +39,36 @@ pub struct SharedDependency {
 
     #[schemars(description = "The resolved version of the dependency")]
     pub version: Version,
+
+    /// SHA-256 digests of the artifacts downloaded on restore
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schemars(description = "The lowercase hex SHA-256 digests of the artifacts downloaded when the dependency was restored. Later restores must download identical artifacts.")]
+    pub checksums: BTreeMap<ArtifactKind, String>,
+
+    /// The publisher key pinned on restore
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The hex encoded ed25519 publisher key pinned when the dependency was restored. Artifacts must be signed with this key, a different key is an error.")]
+    pub publisher_key: Option<String>,
+
+    /// The exact source the dependency was restored from, such as the commit a git branch pointed to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The exact source the dependency was restored from, such as the commit SHA a git branch resolved to.")]
+    pub source: Option<DependencySource>,
+
+    /// The ids of the packages depending on this one, including the root package
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(description = "The ids of the packages that depend on the dependency, including the root package if it is a direct dependency.")]
+    pub required_by: Vec<String>,
+
+    /// The previous id the root package declared a renamed package with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The id the root package declared the dependency with, if the package was renamed since.")]
+    pub declared_id: Option<String>,
+
+    /// The override of the root package that replaced this dependency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The override of the root package that replaced this dependency, if any.")]
+    pub applied_override: Option<DependencyOverride>,
 }
 
 /// qpm.shared.json
@@ -1,8 +1,10 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, path::PathBuf};
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use super::artifact::ArtifactKind;
+
 #[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, Eq, PartialEq, Default)]
 #[serde(rename_all = "camelCase")]
 #[schemars(description = "Additional metadata for the package.")]
@@ -24,16 +26,36 @@ pub struct AdditionalPackageMetadata {
     #[schemars(description = "The link to the shared object file.")]
     pub so_link: Option<String>,
 
+    /// the SHA-256 digest of the so file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The lowercase hex SHA-256 digest of the shared object file.")]
+    pub so_sha256: Option<String>,
+
     /// the link to the so file
     #[serde(skip_serializing_if = "Option::is_none")]
     #[schemars(description = "The link to the static library file.")]
     pub static_link: Option<String>,
 
+    /// the SHA-256 digest of the static file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The lowercase hex SHA-256 digest of the static library file.")]
+    pub static_sha256: Option<String>,
+
     /// the link to the debug .so file
     #[serde(skip_serializing_if = "Option::is_none")]
     #[schemars(description = "The link to the debug shared object file.")]
     pub debug_so_link: Option<String>,
 
+    /// the SHA-256 digest of the debug .so file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The lowercase hex SHA-256 digest of the debug shared object file.")]
+    pub debug_so_sha256: Option<String>,
+
+    /// Detached ed25519 signatures of the artifacts, made with the publisher key, see `signing::sign_artifact`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Hex encoded detached ed25519 signatures of the artifacts by kind, made with the publisher key of the package. Each signs the package id, version, artifact kind and SHA-256 digest of the artifact.")]
+    pub signatures: Option<BTreeMap<ArtifactKind, String>>,
+
     /// the overridden so file name
     #[serde(skip_serializing_if = "Option::is_none")]
     #[schemars(description = "The override name for the shared object file.")]
@@ -49,6 +71,11 @@ pub struct AdditionalPackageMetadata {
     #[schemars(description = "The link to the qmod file.")]
     pub mod_link: Option<String>,
 
+    /// the SHA-256 digest of the qmod
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The lowercase hex SHA-256 digest of the qmod file.")]
+    pub mod_sha256: Option<String>,
+
     /// Branch name of a Github repo. Only used when a valid github url is provided
     #[serde(skip_serializing_if = "Option::is_none")]
     #[schemars(description = "The branch name of a GitHub repository. Only used when a valid GitHub URL is provided.")]
@@ -72,7 +99,12 @@ pub struct AdditionalPackageMetadata {
     /// Whether to generate the a toolchain JSON file [CompileOptions] describing the project setup configuration
     #[serde(skip_serializing_if = "Option::is_none")]
     #[schemars(description = "Path to generate a toolchain JSON file describing the project setup configuration.")]
-    pub toolchain_out: Option<PathBuf>
+    pub toolchain_out: Option<PathBuf>,
+
+    /// Named build profiles such as debug or release, applied on top of this metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Named build profiles, such as debug or release, that override or extend this metadata when selected.")]
+    pub profiles: Option<BTreeMap<String, BuildProfile>>,
 }
 
 /// - compileOptions (QPM.Commands.SupportedPropertiesCommand+CompileOptionsProperty): Additional options for compilation and edits to compilation related files. - Supported in: package
@@ -82,6 +114,9 @@ pub struct AdditionalPackageMetadata {
 /// - cppFeatures - OPTIONAL (System.String[]): Additional C++ features to add.
 /// - cppFlags - OPTIONAL (System.String[]): Additional C++ flags to add.
 /// - cFlags - OPTIONAL (System.String[]): Additional C flags to add.
+/// - defines - OPTIONAL (Dictionary<String, String>): Additional preprocessor defines to add.
+/// - linkFlags - OPTIONAL (System.String[]): Additional linker flags to add.
+/// - linkLibraries - OPTIONAL (System.String[]): Additional system libraries to link against.
 #[derive(Serialize, Deserialize, JsonSchema, Default, Clone, Debug, Hash, Eq, PartialEq)]
 #[serde(rename_all = "camelCase")]
 #[schemars(description = "Additional options for compilation and edits to compilation related files.")]
@@ -111,6 +146,89 @@ pub struct CompileOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[schemars(description = "Additional C flags to add.")]
     pub c_flags: Option<Vec<String>>,
+
+    /// Additional preprocessor defines to add. A null value defines the name without a value.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Additional preprocessor defines to add, by name. A null value defines the name without a value.")]
+    pub defines: Option<BTreeMap<String, Option<String>>>,
+
+    /// Additional linker flags to add.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Additional linker flags to add.")]
+    pub link_flags: Option<Vec<String>>,
+
+    /// Additional system libraries to link against, such as log or android.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Additional system libraries to link against, such as log or android.")]
+    pub link_libraries: Option<Vec<String>>,
+}
+
+/// qpm.json::info::additionalData::profiles.{name}
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, Eq, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Build settings used when building with a specific profile.")]
+pub struct BuildProfile {
+    /// Compile options of the profile
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Compile options of the profile.")]
+    pub compile_options: Option<CompileOptions>,
+
+    /// How the compile options of the profile are combined with the package compile options. If omitted, assume extend
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "How the compile options of the profile are combined with the package compile options. Defaults to extend.")]
+    pub compile_options_mode: Option<CompileOptionsMergeMode>,
+
+    /// Overrides the link to the so file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Overrides the link to the shared object file.")]
+    pub so_link: Option<String>,
+
+    /// Overrides the SHA-256 digest of the so file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Overrides the lowercase hex SHA-256 digest of the shared object file.")]
+    pub so_sha256: Option<String>,
+
+    /// Overrides the link to the static file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Overrides the link to the static library file.")]
+    pub static_link: Option<String>,
+
+    /// Overrides the SHA-256 digest of the static file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Overrides the lowercase hex SHA-256 digest of the static library file.")]
+    pub static_sha256: Option<String>,
+
+    /// Overrides the link to the debug so file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Overrides the link to the debug shared object file.")]
+    pub debug_so_link: Option<String>,
+
+    /// Overrides the SHA-256 digest of the debug so file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Overrides the lowercase hex SHA-256 digest of the debug shared object file.")]
+    pub debug_so_sha256: Option<String>,
+
+    /// Signatures of the overridden links
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Hex encoded detached ed25519 signatures of the overridden links by artifact kind.")]
+    pub signatures: Option<BTreeMap<ArtifactKind, String>>,
+
+    /// Whether to link against the debug so file instead of the so file. If omitted, true for the debug profile only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Whether to link against the debug shared object file instead of the shared object file. Defaults to true for the debug profile and false otherwise.")]
+    pub link_debug_so: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, Hash, Eq, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Describes how compile options are combined with existing compile options.")]
+pub enum CompileOptionsMergeMode {
+    #[default]
+    #[schemars(description = "Append to the existing compile options")]
+    Extend,
+
+    #[schemars(description = "Replace the existing compile options that are specified")]
+    Replace,
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, Eq, PartialEq)]
@@ -145,9 +263,10 @@ pub struct PackageDependencyModifier {
     #[schemars(description = "If the mod dependency should be included in the generated mod.json. Defaults to true.")]
     pub include_qmod: Option<bool>,
 
-    /// Specify any additional files to be downloaded
+    /// Specify any additional files to be downloaded.
+    /// http(s) urls are downloaded, anything else is a path in the source of the dependency
     #[serde(skip_serializing_if = "Option::is_none")]
-    #[schemars(description = "Additional files to be downloaded.")]
+    #[schemars(description = "Additional files to be downloaded. http:// and https:// URLs are downloaded, anything else is a path in the source of the dependency.")]
     pub extra_files: Option<Vec<String>>,
 
     /// Whether or not the dependency is private and should be used in restore
@@ -171,4 +290,15 @@ pub struct PackageDependencyModifier {
     #[serde(rename = "required")]
     #[schemars(description = "Whether the mod is optional or required. If omitted, assume true.")]
     pub required: Option<bool>,
+
+    /// Compile options to use for this dependency in addition to the ones it publishes
+    /// Include paths are relative to the dependency's include directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Compile options overriding the ones published by the dependency. Include paths are relative to the include directory of the dependency.")]
+    pub compile_options: Option<CompileOptions>,
+
+    /// How the compile options override is combined with the published compile options. If omitted, assume extend
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "How the compile options override is combined with the compile options published by the dependency. Extend appends lists and overrides defines with the same name, replace replaces every option that is specified. Defaults to extend.")]
+    pub compile_options_mode: Option<CompileOptionsMergeMode>,
 }
@@ -0,0 +1,33 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Rewrites of artifact and source urls to mirrors
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, Eq, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Rewrites of artifact and source URLs to mirrors, so restores don't depend on the availability of the original hosts.")]
+pub struct MirrorConfig {
+    /// Rules are matched in order, every matching rule adds its mirrors
+    #[serde(default)]
+    #[schemars(description = "The rewrite rules. Every rule whose prefix matches a URL adds its mirrors, in order.")]
+    pub rules: Vec<MirrorRule>,
+}
+
+/// mirrors.rules[]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Rewrites URLs starting with a prefix to mirror prefixes.")]
+pub struct MirrorRule {
+    /// e.g. `https://github.com/`
+    #[schemars(description = "The URL prefix to rewrite, such as https://github.com/.")]
+    pub prefix: String,
+
+    /// Replacements of the prefix, tried in order
+    #[schemars(description = "Replacements of the prefix, tried in order.")]
+    pub mirrors: Vec<String>,
+
+    /// Whether the original url is tried after the mirrors failed. If omitted, assume true.
+    /// The original url is always used if no rule gives a mirror for it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Whether the original URL is tried after all mirrors failed. Defaults to true. The original URL is always used if no matching rule has a mirror.")]
+    pub fallback_to_original: Option<bool>,
+}
@@ -3,4 +3,10 @@ pub mod dependency;
 pub mod extra;
 pub mod backend;
 pub mod workspace;
+pub mod toolchain;
+pub mod artifact;
+pub mod source;
+pub mod mirror;
+pub mod auth;
+pub mod user_config;
 mod version_req;
\ No newline at end of file
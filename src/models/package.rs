@@ -6,6 +6,7 @@ use serde::{Deserialize, Deserializer, Serialize};
 
 use super::{
     extra::{AdditionalPackageMetadata, PackageDependencyModifier},
+    source::DependencySource,
     workspace::WorkspaceConfig,
 };
 
@@ -48,6 +49,11 @@ pub struct PackageConfig {
 
     #[schemars(description = "The dependencies of the package.")]
     pub dependencies: Vec<PackageDependency>,
+
+    /// Replacements for dependencies anywhere in the dependency tree, only applied when this is the root package
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(description = "Replacements for dependencies anywhere in the dependency tree, such as a local fork of a transitive dependency. Only applied when restoring this package, not packages depending on it.")]
+    pub overrides: Vec<DependencyOverride>,
 }
 
 impl Default for PackageConfig {
@@ -61,10 +67,12 @@ impl Default for PackageConfig {
                 id: Default::default(),
                 version: Version::new(1, 0, 0),
                 url: Default::default(),
+                publisher_key: Default::default(),
                 additional_data: Default::default(),
             },
             shared_dir: Default::default(),
             workspace: Default::default(),
+            overrides: Default::default(),
         }
     }
 }
@@ -86,6 +94,11 @@ pub struct PackageMetadata {
     #[schemars(description = "The website for the package.")]
     pub url: Option<String>,
 
+    /// Hex encoded ed25519 public key the artifacts of the package are signed with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The hex encoded ed25519 public key of the publisher, used to verify the signatures of the package artifacts.")]
+    pub publisher_key: Option<String>,
+
     #[schemars(description = "Additional metadata for the package.")]
     pub additional_data: AdditionalPackageMetadata,
 }
@@ -103,10 +116,63 @@ pub struct PackageDependency {
     #[schemars(schema_with = "make_version_req_schema")]
     pub version_range: VersionReq,
 
+    /// Where to restore the dependency from. If omitted, the legacy localPath or the registry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Where to restore the dependency from. If omitted, localPath is used when set, otherwise the dependency is restored from the registry.")]
+    pub source: Option<DependencySource>,
+
     #[schemars(description = "Additional metadata for the dependency")]
     pub additional_data: PackageDependencyModifier,
 }
 
+// qpm.json::overrides[]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Replaces a dependency wherever it is required in the dependency tree.")]
+pub struct DependencyOverride {
+    #[schemars(description = "The unique identifier of the dependency to replace")]
+    pub id: String,
+
+    /// Only replace requirements declaring exactly this range, all requirements of the id if omitted
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_optional_version_req"
+    )]
+    #[schemars(description = "Only replace requirements of the dependency declaring exactly this version range. If omitted, every requirement of the dependency is replaced.")]
+    #[schemars(schema_with = "make_version_req_schema")]
+    pub version_range: Option<VersionReq>,
+
+    /// The version range to resolve instead of the declared one
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_optional_version_req"
+    )]
+    #[schemars(description = "The version range to resolve instead of the declared one.")]
+    #[schemars(schema_with = "make_version_req_schema")]
+    pub version: Option<VersionReq>,
+
+    /// Where to restore the dependency from instead, paths are relative to this package
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Where to restore the dependency from instead, such as a local path or a git commit. Paths are relative to this package.")]
+    pub source: Option<DependencySource>,
+}
+
+/// Parses ranges the same way as `PackageDependency::version_range`
+fn deserialize_optional_version_req<'de, D>(deserializer: D) -> Result<Option<VersionReq>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct CursedVersionReq(
+        #[serde(deserialize_with = "cursed_semver_parser::deserialize")] VersionReq,
+    );
+
+    let opt = Option::<CursedVersionReq>::deserialize(deserializer)?;
+    Ok(opt.map(|range| range.0))
+}
+
 fn deserialize_null_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
     T: Default + Deserialize<'de>,
@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::extra::{AdditionalPackageMetadata, PackageDependencyModifier};
+
+/// Where a dependency comes from.
+/// Replaces the source location spread across url, branchName, subFolder and localPath,
+/// which are still read when no source is given.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, Eq, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[schemars(description = "Where a dependency is restored from.")]
+pub enum DependencySource {
+    /// Published to a registry
+    #[schemars(description = "A package published to a registry.")]
+    Registry {
+        /// The registry url, the default registry if omitted
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[schemars(description = "The URL of the registry. Defaults to the default registry if omitted.")]
+        registry: Option<String>,
+    },
+
+    /// A git repository
+    #[schemars(description = "A git repository.")]
+    Git {
+        #[schemars(description = "The URL of the git repository.")]
+        url: String,
+
+        /// The branch, tag or commit to check out, the default branch if omitted
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[schemars(description = "The branch, tag or commit to check out. Defaults to the default branch if omitted.")]
+        reference: Option<GitReference>,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[schemars(description = "Sub-folder of the repository containing the package.")]
+        sub_folder: Option<String>,
+    },
+
+    /// A directory on disk
+    #[schemars(description = "A directory on disk.")]
+    Path {
+        /// Relative to the directory of the package depending on it
+        #[schemars(description = "The path to the package directory, relative to the directory of the package depending on it.")]
+        path: PathBuf,
+    },
+
+    /// An archive downloaded from a url
+    #[schemars(description = "A zip archive downloaded from a URL.")]
+    Url {
+        #[schemars(description = "The URL of the archive.")]
+        url: String,
+
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        #[schemars(description = "Sub-folder of the archive containing the package.")]
+        sub_folder: Option<String>,
+    },
+}
+
+/// A git reference to check out
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "A git reference to check out.")]
+pub enum GitReference {
+    #[schemars(description = "The head of a branch.")]
+    Branch(String),
+
+    #[schemars(description = "A tag.")]
+    Tag(String),
+
+    #[schemars(description = "A full commit SHA.")]
+    Rev(String),
+}
+
+impl DependencySource {
+    /// The source a package declares through the legacy `url`, `branchName` and `subFolder` fields.
+    /// Urls of zip archives are downloaded as is, any other url is treated as a git repository.
+    pub fn from_legacy_metadata(
+        url: Option<&str>,
+        additional_data: &AdditionalPackageMetadata,
+    ) -> Option<Self> {
+        let url = url?;
+        let sub_folder = additional_data.sub_folder.clone();
+
+        if is_archive_url(url) {
+            return Some(DependencySource::Url {
+                url: url.to_string(),
+                sub_folder,
+            });
+        }
+
+        Some(DependencySource::Git {
+            url: url.to_string(),
+            reference: additional_data.branch_name.clone().map(GitReference::Branch),
+            sub_folder,
+        })
+    }
+
+    /// The source a dependency declares through the legacy `localPath` field, the registry otherwise
+    pub fn from_legacy_modifier(modifier: &PackageDependencyModifier) -> Self {
+        match &modifier.local_path {
+            Some(path) => DependencySource::Path { path: path.into() },
+            None => DependencySource::Registry { registry: None },
+        }
+    }
+
+    /// Pins a git source to the commit its reference resolved to, for the lockfile
+    pub fn with_commit(self, commit: &str) -> Self {
+        match self {
+            DependencySource::Git {
+                url, sub_folder, ..
+            } => DependencySource::Git {
+                url,
+                reference: Some(GitReference::Rev(commit.to_string())),
+                sub_folder,
+            },
+            source => source,
+        }
+    }
+
+    pub fn get_sub_folder(&self) -> Option<&String> {
+        match self {
+            DependencySource::Git { sub_folder, .. } | DependencySource::Url { sub_folder, .. } => {
+                sub_folder.as_ref()
+            }
+            DependencySource::Registry { .. } | DependencySource::Path { .. } => None,
+        }
+    }
+}
+
+/// Whether a url points to a zip archive rather than a repository
+pub fn is_archive_url(url: &str) -> bool {
+    url.split(['?', '#'])
+        .next()
+        .is_some_and(|path| path.to_ascii_lowercase().ends_with(".zip"))
+}
@@ -0,0 +1,80 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use schemars::JsonSchema;
+use semver::VersionReq;
+use serde::{Deserialize, Serialize};
+
+use super::extra::DependencyLibType;
+
+use crate::models::version_req::make_version_req_schema;
+
+/// toolchain.json, written to AdditionalPackageMetadata::toolchain_out.
+///
+/// Build scripts generating CMake from it map `defines` to `target_compile_definitions`
+/// (see `CompileOptionsExtensions::get_definitions`), `link_flags` to `target_link_options`
+/// and `system_libraries` to `target_link_libraries`. No CMake is generated by this crate.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Toolchain describing the project setup configuration.")]
+pub struct Toolchain {
+    /// Include paths of the package and its dependencies
+    #[schemars(description = "Include paths of the package and its restored dependencies.")]
+    pub include_paths: Vec<PathBuf>,
+
+    /// System include paths of the package and its dependencies
+    #[schemars(description = "System include paths of the package and its restored dependencies.")]
+    pub system_includes: Vec<PathBuf>,
+
+    /// C++ flags of the package and its dependencies
+    #[schemars(description = "C++ flags of the package and its restored dependencies.")]
+    pub cpp_flags: Vec<String>,
+
+    /// C flags of the package and its dependencies
+    #[schemars(description = "C flags of the package and its restored dependencies.")]
+    pub c_flags: Vec<String>,
+
+    /// Preprocessor defines of the package and its dependencies
+    #[schemars(description = "Preprocessor defines of the package and its restored dependencies. A null value defines the name without a value.")]
+    pub defines: BTreeMap<String, Option<String>>,
+
+    /// Linker flags of the package and its dependencies
+    #[schemars(description = "Linker flags of the package and its restored dependencies.")]
+    pub link_flags: Vec<String>,
+
+    /// System libraries to link against, from `CompileOptions::link_libraries`
+    #[schemars(description = "System libraries the package and its restored dependencies link against, such as log or android.")]
+    pub system_libraries: Vec<String>,
+
+    /// Binaries to link against
+    #[schemars(description = "The libraries of the restored dependencies to link against.")]
+    pub linked_libraries: Vec<ToolchainLibrary>,
+
+    /// NDK Version Range
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The NDK version range.")]
+    #[schemars(schema_with = "make_version_req_schema")]
+    pub ndk: Option<VersionReq>,
+
+    /// The directory dependencies are restored to
+    #[schemars(description = "The directory where dependencies are stored.")]
+    pub extern_dir: PathBuf,
+
+    /// The directory shared files are stored
+    #[schemars(description = "The directory where shared files are stored.")]
+    pub shared_dir: PathBuf,
+}
+
+/// toolchain.json::linkedLibraries[]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "A library to link against.")]
+pub struct ToolchainLibrary {
+    #[schemars(description = "The unique identifier of the dependency providing the library.")]
+    pub id: String,
+
+    #[schemars(description = "How the library is linked.")]
+    pub lib_type: DependencyLibType,
+
+    #[schemars(description = "The path to the library file.")]
+    pub path: PathBuf,
+}
@@ -0,0 +1,102 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{auth::AuthConfig, mirror::MirrorConfig};
+
+/// qpm.settings.json, stored in the user config directory or next to a qpm.json.
+/// Settings of the project take precedence over those of the user,
+/// except that settings receiving or referencing secrets can only be set by the user.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "Machine or project level settings of the QPM tooling. Settings receiving or referencing secrets can only be set in the user settings, project settings setting them are rejected.")]
+pub struct UserConfig {
+    /// Where downloaded artifacts are cached
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The directory downloaded artifacts are cached in.")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// The registry packages are restored from and published to. User settings only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "The URL of the default registry. Only allowed in the user settings, since the registry token is sent to it.")]
+    pub registry_url: Option<String>,
+
+    /// Where the token of the default registry is read from. User settings only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Where the token of the default registry is read from. Only allowed in the user settings.")]
+    pub registry_token: Option<TokenReference>,
+
+    /// Additional registries by name. User settings only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Additional registries by name. Only allowed in the user settings, since their tokens are sent to them.")]
+    pub registries: Option<BTreeMap<String, RegistryConfig>>,
+
+    /// Directories searched for installed NDKs, in order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Directories searched for installed NDKs, in order.")]
+    pub ndk_paths: Option<Vec<PathBuf>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Mirrors of artifact and source URLs.")]
+    pub mirrors: Option<MirrorConfig>,
+
+    /// Authentication of artifact and source urls by host. User settings only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Authentication of artifact and source URLs by host. Only allowed in the user settings, since it decides where secrets are sent.")]
+    pub auth: Option<AuthConfig>,
+
+    /// How versions are picked when resolving dependencies. If omitted, assume highest
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "How versions are picked when resolving dependencies. Defaults to highest.")]
+    pub resolution_strategy: Option<ResolutionStrategy>,
+}
+
+/// qpm.settings.json::registries[]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "A registry packages can be restored from.")]
+pub struct RegistryConfig {
+    #[schemars(description = "The URL of the registry.")]
+    pub url: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(description = "Where the token of the registry is read from.")]
+    pub token: Option<TokenReference>,
+}
+
+/// Where a secret is read from, so it is never stored in the settings themselves
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, Hash, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+#[schemars(description = "Where a token is read from. Tokens are never stored in the settings themselves.")]
+pub enum TokenReference {
+    #[schemars(description = "An environment variable.")]
+    Env {
+        #[schemars(description = "The name of the environment variable.")]
+        name: String,
+    },
+
+    #[schemars(description = "A file only readable by the user, containing the token.")]
+    File {
+        #[schemars(description = "The path to the file.")]
+        path: PathBuf,
+    },
+
+    #[schemars(description = "A command printing the token, like a git credential helper.")]
+    Helper {
+        #[schemars(description = "The command and its arguments.")]
+        command: Vec<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, Hash, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+#[schemars(description = "How versions are picked when resolving dependencies.")]
+pub enum ResolutionStrategy {
+    #[default]
+    #[schemars(description = "The newest version matching every range.")]
+    Highest,
+
+    #[schemars(description = "The oldest version matching every range, to check the declared lower bounds.")]
+    Lowest,
+}
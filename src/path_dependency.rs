@@ -0,0 +1,251 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, ErrorKind},
+    path::{Component, Path, PathBuf},
+};
+
+use semver::{Version, VersionReq};
+use thiserror::Error;
+
+use crate::{
+    extensions::{
+        package_config::PACKAGE_FILE_NAME, package_dependency::PackageDependencyExtensions,
+    },
+    models::{
+        package::{PackageConfig, PackageDependency},
+        source::DependencySource,
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum PathDependencyError {
+    #[error("local package {0:?} does not exist")]
+    NotFound(PathBuf),
+
+    #[error("symlink loop while resolving {0:?}")]
+    SymlinkLoop(PathBuf),
+
+    #[error("local package at {path:?} is {found} but {expected} was expected")]
+    IdMismatch {
+        path: PathBuf,
+        expected: String,
+        found: String,
+    },
+
+    #[error("local package {id} at {path:?} is version {version} which does not match {range}")]
+    VersionMismatch {
+        path: PathBuf,
+        id: String,
+        version: Version,
+        range: VersionReq,
+    },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// A dependency restored from a directory on disk
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathDependency {
+    /// The canonical directory of the local package
+    pub dir: PathBuf,
+    /// The qpm.json of the local package, used as the metadata of the dependency
+    pub config: PackageConfig,
+}
+
+/// The path a dependency is restored from, if it is a path dependency.
+/// `manifest_dir` is the directory of the qpm.json declaring the dependency.
+pub fn get_dependency_path(manifest_dir: &Path, dependency: &PackageDependency) -> Option<PathBuf> {
+    match dependency.get_source() {
+        DependencySource::Path { path } => Some(manifest_dir.join(path)),
+        _ => None,
+    }
+}
+
+/// Loads the local package of a path dependency declared in the qpm.json in `manifest_dir`,
+/// checking that it is the package the dependency asks for. Returns `None` for other dependencies.
+pub fn load_path_dependency(
+    manifest_dir: &Path,
+    dependency: &PackageDependency,
+) -> Result<Option<PathDependency>, PathDependencyError> {
+    get_dependency_path(manifest_dir, dependency)
+        .map(|path| load_local_package(&path, &dependency.id, &dependency.version_range))
+        .transpose()
+}
+
+/// Loads the qpm.json of the package in `path`, checking that it is `id` and its version matches `range`
+pub fn load_local_package(
+    path: &Path,
+    id: &str,
+    range: &VersionReq,
+) -> Result<PathDependency, PathDependencyError> {
+    let dir = canonicalize(path)?;
+    let config_path = dir.join(PACKAGE_FILE_NAME);
+    let config: PackageConfig = match File::open(&config_path) {
+        Ok(file) => serde_json::from_reader(BufReader::new(file))?,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Err(PathDependencyError::NotFound(config_path))
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if config.info.id != id {
+        return Err(PathDependencyError::IdMismatch {
+            path: dir,
+            expected: id.to_string(),
+            found: config.info.id,
+        });
+    }
+    if !range.matches(&config.info.version) {
+        return Err(PathDependencyError::VersionMismatch {
+            path: dir,
+            id: config.info.id,
+            version: config.info.version,
+            range: range.clone(),
+        });
+    }
+
+    Ok(PathDependency { dir, config })
+}
+
+/// How many symlinks are followed while resolving a path before it is considered a loop, the limit of Linux
+pub const MAX_SYMLINK_HOPS: usize = 40;
+
+/// Resolves every symlink in a path like [`fs::canonicalize`],
+/// but reports a symlink loop instead of failing with an OS specific error.
+/// Like the OS, a path following more than [`MAX_SYMLINK_HOPS`] symlinks is a loop,
+/// so the same link may be followed more than once, as in `a/link/../link/x`.
+pub fn canonicalize(path: &Path) -> Result<PathBuf, PathDependencyError> {
+    let path = if path.is_relative() {
+        std::env::current_dir()?.join(path)
+    } else {
+        path.to_path_buf()
+    };
+
+    let mut resolved = PathBuf::new();
+    let mut pending: Vec<PathBuf> = path
+        .components()
+        .rev()
+        .map(|c| c.as_os_str().into())
+        .collect();
+    let mut hops = 0;
+
+    while let Some(component) = pending.pop() {
+        match component.components().next() {
+            Some(Component::CurDir) | None => {}
+            Some(Component::ParentDir) => {
+                resolved.pop();
+            }
+            Some(Component::Prefix(_) | Component::RootDir) => resolved.push(&component),
+            Some(Component::Normal(name)) => {
+                let next = resolved.join(name);
+                let metadata = match fs::symlink_metadata(&next) {
+                    Ok(metadata) => metadata,
+                    Err(e) if e.kind() == ErrorKind::NotFound => {
+                        return Err(PathDependencyError::NotFound(next))
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+
+                if !metadata.file_type().is_symlink() {
+                    resolved = next;
+                    continue;
+                }
+
+                hops += 1;
+                if hops > MAX_SYMLINK_HOPS {
+                    return Err(PathDependencyError::SymlinkLoop(path));
+                }
+
+                // the target replaces the link, relative targets are resolved from the link's directory
+                let target = fs::read_link(&next)?;
+                if target.is_absolute() {
+                    resolved = PathBuf::new();
+                }
+                pending.extend(
+                    target
+                        .components()
+                        .rev()
+                        .map(|c| PathBuf::from(c.as_os_str())),
+                );
+            }
+        }
+    }
+
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// A temporary directory and its canonical path, symlinks in the temp dir itself are resolved
+    fn get_temp_dir() -> (TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fs::canonicalize(dir.path()).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn canonicalizes_dot_segments() {
+        let (_temp_dir, dir) = get_temp_dir();
+        fs::create_dir_all(dir.join("a/b")).unwrap();
+
+        assert_eq!(
+            canonicalize(&dir.join("a/./b/../b")).unwrap(),
+            dir.join("a/b")
+        );
+        assert!(matches!(
+            canonicalize(&dir.join("a/missing")),
+            Err(PathDependencyError::NotFound(path)) if path == dir.join("a/missing")
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follows_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let (_temp_dir, dir) = get_temp_dir();
+        fs::create_dir_all(dir.join("real/x")).unwrap();
+        symlink("real", dir.join("link")).unwrap();
+        symlink(dir.join("link"), dir.join("absolute")).unwrap();
+
+        assert_eq!(
+            canonicalize(&dir.join("link/x")).unwrap(),
+            dir.join("real/x")
+        );
+        assert_eq!(
+            canonicalize(&dir.join("absolute/x")).unwrap(),
+            dir.join("real/x")
+        );
+        // following the same link twice is not a loop
+        assert_eq!(
+            canonicalize(&dir.join("link/../link/x")).unwrap(),
+            dir.join("real/x")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reports_symlink_loops() {
+        use std::os::unix::fs::symlink;
+
+        let (_temp_dir, dir) = get_temp_dir();
+        symlink("b", dir.join("a")).unwrap();
+        symlink("a", dir.join("b")).unwrap();
+        symlink("self", dir.join("self")).unwrap();
+
+        for path in [dir.join("a"), dir.join("self/x")] {
+            assert!(matches!(
+                canonicalize(&path),
+                Err(PathDependencyError::SymlinkLoop(looped)) if looped == path
+            ));
+        }
+    }
+}
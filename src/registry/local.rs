@@ -0,0 +1,416 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Write},
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use chrono::{DateTime, Utc};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    extensions::shared_package_config::SHARED_PACKAGE_FILE_NAME,
+    models::{
+        backend::{PackageAlias, PackageListing, PackageVersion, PackageVersionListing},
+        dependency::SharedPackageConfig,
+    },
+};
+
+use super::{check_package_id, Registry, RegistryError};
+
+pub const METADATA_FILE_NAME: &str = "metadata.json";
+
+/// Makes the temporary files of concurrent publishes in one process unique
+static TMP_FILE_COUNT: AtomicU64 = AtomicU64::new(0);
+
+/// Registry metadata of a package that is not part of its published configs,
+/// stored in `{root}/{id}/metadata.json`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalPackageMetadata {
+    #[serde(default)]
+    pub deprecated: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deprecation_reason: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+
+    /// Yanked versions and why they were yanked
+    #[serde(default)]
+    pub yanked: BTreeMap<Version, Option<String>>,
+
+    /// The id the package was renamed to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renamed_to: Option<String>,
+}
+
+/// A registry stored in a local directory, laid out as `{root}/{id}/{version}/qpm.shared.json`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalRegistry {
+    pub root: PathBuf,
+}
+
+impl LocalRegistry {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// The directory all versions of a package are stored in
+    pub fn get_package_dir(&self, id: &str) -> Result<PathBuf, RegistryError> {
+        check_package_id(id)?;
+        Ok(self.root.join(id))
+    }
+
+    /// The directory a version of a package is stored in
+    pub fn get_version_dir(&self, id: &str, version: &Version) -> Result<PathBuf, RegistryError> {
+        Ok(self.get_package_dir(id)?.join(version.to_string()))
+    }
+
+    pub fn get_metadata(&self, id: &str) -> Result<LocalPackageMetadata, RegistryError> {
+        let path = self.get_package_dir(id)?.join(METADATA_FILE_NAME);
+        match File::open(path) {
+            Ok(file) => Ok(serde_json::from_reader(BufReader::new(file))?),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Default::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn set_metadata(
+        &self,
+        id: &str,
+        metadata: &LocalPackageMetadata,
+    ) -> Result<(), RegistryError> {
+        let dir = self.get_package_dir(id)?;
+        if !dir.is_dir() {
+            return Err(RegistryError::PackageNotFound(id.to_string()));
+        }
+
+        let file = File::create(dir.join(METADATA_FILE_NAME))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), metadata)?;
+        Ok(())
+    }
+}
+
+impl Registry for LocalRegistry {
+    fn get_versions(&self, id: &str) -> Result<Vec<PackageVersion>, RegistryError> {
+        let entries = match fs::read_dir(self.get_package_dir(id)?) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(RegistryError::PackageNotFound(id.to_string()))
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut versions = vec![];
+        for entry in entries {
+            let entry = entry?;
+            if !entry.path().join(SHARED_PACKAGE_FILE_NAME).is_file() {
+                continue;
+            }
+
+            // skip anything that isn't a version directory
+            let Some(version) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| Version::parse(name).ok())
+            else {
+                continue;
+            };
+
+            versions.push(PackageVersion {
+                id: id.to_string(),
+                version,
+            });
+        }
+
+        versions.sort_by(|a, b| a.version.cmp(&b.version));
+        Ok(versions)
+    }
+
+    fn get_shared_package(
+        &self,
+        id: &str,
+        version: &Version,
+    ) -> Result<SharedPackageConfig, RegistryError> {
+        let path = self
+            .get_version_dir(id, version)?
+            .join(SHARED_PACKAGE_FILE_NAME);
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(RegistryError::VersionNotFound(
+                    id.to_string(),
+                    version.clone(),
+                ))
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    fn publish(&self, package: &SharedPackageConfig) -> Result<(), RegistryError> {
+        let info = &package.config.info;
+        let dir = self.get_version_dir(&info.id, &info.version)?;
+        let path = dir.join(SHARED_PACKAGE_FILE_NAME);
+        let json = serde_json::to_vec_pretty(package)?;
+
+        // the complete file is linked into place, which fails if it exists, so readers never see a partial
+        // config and concurrent publishes of a version can't overwrite each other
+        fs::create_dir_all(&dir)?;
+        let count = TMP_FILE_COUNT.fetch_add(1, Ordering::Relaxed);
+        let tmp_path = dir.join(format!(
+            "{SHARED_PACKAGE_FILE_NAME}.{}.{count}.tmp",
+            process::id()
+        ));
+        let result = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)
+            .and_then(|mut file| file.write_all(&json))
+            .and_then(|_| fs::hard_link(&tmp_path, path));
+        fs::remove_file(&tmp_path).ok();
+
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => Err(RegistryError::AlreadyPublished(
+                info.id.clone(),
+                info.version.clone(),
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn get_listing(&self, id: &str) -> Result<PackageListing, RegistryError> {
+        let metadata = self.get_metadata(id)?;
+
+        let mut versions = vec![];
+        for version in self.get_versions(id)? {
+            let path = self
+                .get_version_dir(id, &version.version)?
+                .join(SHARED_PACKAGE_FILE_NAME);
+            let published_at: DateTime<Utc> = fs::metadata(&path)?.modified()?.into();
+            let yank_reason = metadata.yanked.get(&version.version);
+
+            versions.push(PackageVersionListing {
+                config: self.get_shared_package(id, &version.version)?,
+                version: version.version,
+                published_at,
+                yanked: yank_reason.is_some(),
+                yank_reason: yank_reason.cloned().flatten(),
+                deprecated: metadata.deprecated,
+                downloads: 0,
+            });
+        }
+
+        Ok(PackageListing {
+            id: id.to_string(),
+            owners: vec![],
+            downloads: 0,
+            deprecated: metadata.deprecated,
+            deprecation_reason: metadata.deprecation_reason,
+            replacement: metadata.replacement,
+            versions,
+        })
+    }
+
+    fn yank(&self, id: &str, version: &Version, reason: Option<&str>) -> Result<(), RegistryError> {
+        let path = self
+            .get_version_dir(id, version)?
+            .join(SHARED_PACKAGE_FILE_NAME);
+        if !path.is_file() {
+            return Err(RegistryError::VersionNotFound(
+                id.to_string(),
+                version.clone(),
+            ));
+        }
+
+        let mut metadata = self.get_metadata(id)?;
+        metadata
+            .yanked
+            .insert(version.clone(), reason.map(str::to_string));
+        self.set_metadata(id, &metadata)
+    }
+
+    fn deprecate(
+        &self,
+        id: &str,
+        reason: Option<&str>,
+        replacement: Option<&str>,
+    ) -> Result<(), RegistryError> {
+        let mut metadata = self.get_metadata(id)?;
+        metadata.deprecated = true;
+        metadata.deprecation_reason = reason.map(str::to_string);
+        metadata.replacement = replacement.map(str::to_string);
+        self.set_metadata(id, &metadata)
+    }
+
+    fn get_alias(&self, id: &str) -> Result<Option<String>, RegistryError> {
+        Ok(self.get_metadata(id)?.renamed_to)
+    }
+
+    fn add_alias(&self, alias: &PackageAlias) -> Result<(), RegistryError> {
+        // validates the new id
+        self.get_package_dir(&alias.id)?;
+        fs::create_dir_all(self.get_package_dir(&alias.alias)?)?;
+
+        let mut metadata = self.get_metadata(&alias.alias)?;
+        metadata.renamed_to = Some(alias.id.clone());
+        self.set_metadata(&alias.alias, &metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::VersionReq;
+
+    use super::*;
+    use crate::models::dependency::{Dependency, SharedDependency};
+
+    fn package(version: &str) -> SharedPackageConfig {
+        let mut shared = SharedPackageConfig {
+            config: Default::default(),
+            restored_dependencies: vec![],
+        };
+        shared.config.info.id = "a".to_string();
+        shared.config.info.version = Version::parse(version).unwrap();
+        shared
+    }
+
+    #[test]
+    fn publishes_each_version_once() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let registry = LocalRegistry::new(temp_dir.path());
+
+        registry.publish(&package("1.0.0")).unwrap();
+        assert!(matches!(
+            registry.publish(&package("1.0.0")),
+            Err(RegistryError::AlreadyPublished(id, _)) if id == "a"
+        ));
+        registry.publish(&package("1.1.0")).unwrap();
+
+        let versions: Vec<_> = registry
+            .get_versions("a")
+            .unwrap()
+            .into_iter()
+            .map(|v| v.version.to_string())
+            .collect();
+        assert_eq!(versions, ["1.0.0", "1.1.0"]);
+
+        // no temporary files are left behind
+        let version_dir = registry
+            .get_version_dir("a", &Version::new(1, 0, 0))
+            .unwrap();
+        let files: Vec<_> = fs::read_dir(version_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files, [SHARED_PACKAGE_FILE_NAME]);
+    }
+
+    #[test]
+    fn rejects_ids_outside_the_registry() {
+        let registry = LocalRegistry::new("registry");
+        for id in ["", ".", "..", "a/b", "a\\b", "/abs"] {
+            assert!(
+                matches!(
+                    registry.get_package_dir(id),
+                    Err(RegistryError::InvalidPackageId(_))
+                ),
+                "{id}"
+            );
+        }
+        assert_eq!(
+            registry.get_package_dir("a").unwrap(),
+            PathBuf::from("registry").join("a")
+        );
+    }
+
+    #[test]
+    fn skips_yanked_versions_unless_locked() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let registry = LocalRegistry::new(temp_dir.path());
+        for version in ["1.0.0", "1.1.0", "1.2.0"] {
+            registry.publish(&package(version)).unwrap();
+        }
+
+        let version = Version::new(1, 1, 0);
+        registry
+            .yank("a", &version, Some("crashes on load"))
+            .unwrap();
+        assert!(matches!(
+            registry.yank("a", &Version::new(2, 0, 0), None),
+            Err(RegistryError::VersionNotFound(..))
+        ));
+
+        let listing = registry.get_listing("a").unwrap();
+        let yanked: Vec<_> = listing
+            .versions
+            .iter()
+            .map(|v| (v.version.to_string(), v.yank_reason.as_deref()))
+            .collect();
+        assert_eq!(
+            yanked,
+            [
+                ("1.0.0".to_string(), None),
+                ("1.1.0".to_string(), Some("crashes on load")),
+                ("1.2.0".to_string(), None)
+            ]
+        );
+
+        let resolvable = |locked: Option<&SharedPackageConfig>| -> Vec<_> {
+            registry
+                .get_resolvable_versions("a", locked)
+                .unwrap()
+                .into_iter()
+                .map(|v| v.version.to_string())
+                .collect()
+        };
+        assert_eq!(resolvable(None), ["1.0.0", "1.2.0"]);
+
+        #[allow(deprecated)]
+        let locked = SharedPackageConfig {
+            config: Default::default(),
+            restored_dependencies: vec![SharedDependency {
+                dependency: Dependency {
+                    id: "a".to_string(),
+                    version_range: VersionReq::STAR,
+                    additional_data: Default::default(),
+                },
+                version,
+                checksums: Default::default(),
+                publisher_key: None,
+                source: None,
+                required_by: vec![],
+                declared_id: None,
+                applied_override: None,
+            }],
+        };
+        assert_eq!(resolvable(Some(&locked)), ["1.0.0", "1.1.0", "1.2.0"]);
+    }
+
+    #[test]
+    fn deprecates_packages() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let registry = LocalRegistry::new(temp_dir.path());
+        assert!(matches!(
+            registry.deprecate("a", None, None),
+            Err(RegistryError::PackageNotFound(_))
+        ));
+
+        registry.publish(&package("1.0.0")).unwrap();
+        registry.deprecate("a", Some("renamed"), Some("b")).unwrap();
+
+        let listing = registry.get_listing("a").unwrap();
+        assert!(listing.deprecated);
+        assert_eq!(listing.deprecation_reason.as_deref(), Some("renamed"));
+        assert_eq!(listing.replacement.as_deref(), Some("b"));
+        assert!(listing.versions.iter().all(|v| v.deprecated && !v.yanked));
+    }
+}
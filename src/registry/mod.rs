@@ -0,0 +1,153 @@
+pub mod http;
+pub mod local;
+pub mod publish;
+
+use std::path::Path;
+
+use semver::Version;
+use thiserror::Error;
+
+use crate::{
+    credentials::CredentialError,
+    models::{
+        backend::{PackageAlias, PackageListing, PackageVersion},
+        dependency::SharedPackageConfig,
+        package::PackageConfig,
+    },
+};
+
+#[derive(Debug, Error)]
+pub enum RegistryError {
+    #[error("package {0} was not found")]
+    PackageNotFound(String),
+
+    #[error("version {1} of package {0} was not found")]
+    VersionNotFound(String, Version),
+
+    #[error("version {1} of package {0} is already published")]
+    AlreadyPublished(String, Version),
+
+    #[error("invalid package id {0:?}")]
+    InvalidPackageId(String),
+
+    #[error("package aliases form a cycle: {}", .0.join(" -> "))]
+    AliasCycle(Vec<String>),
+
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("registry responded with status {status}: {message}")]
+    Http { status: u16, message: String },
+
+    #[error("failed to reach registry")]
+    Transport(#[source] http::TransportError),
+
+    #[error(transparent)]
+    Credential(#[from] CredentialError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// Checks that a package id is a single path segment, so it can't point outside of a registry
+pub fn check_package_id(id: &str) -> Result<(), RegistryError> {
+    let is_valid = !id.is_empty()
+        && id != "."
+        && id != ".."
+        && !id.contains(['/', '\\'])
+        && Path::new(id).is_relative();
+    if !is_valid {
+        return Err(RegistryError::InvalidPackageId(id.to_string()));
+    }
+
+    Ok(())
+}
+
+/// A source of published packages
+pub trait Registry {
+    /// All published versions of a package, sorted from oldest to newest
+    fn get_versions(&self, id: &str) -> Result<Vec<PackageVersion>, RegistryError>;
+
+    /// The shared package config published for a version of a package
+    fn get_shared_package(
+        &self,
+        id: &str,
+        version: &Version,
+    ) -> Result<SharedPackageConfig, RegistryError>;
+
+    /// The package config published for a version of a package
+    fn get_package(&self, id: &str, version: &Version) -> Result<PackageConfig, RegistryError> {
+        Ok(self.get_shared_package(id, version)?.config)
+    }
+
+    /// Publishes a package, failing if its version is already published
+    fn publish(&self, package: &SharedPackageConfig) -> Result<(), RegistryError>;
+
+    /// A package with all of its published versions, including yanked ones
+    fn get_listing(&self, id: &str) -> Result<PackageListing, RegistryError>;
+
+    /// Marks a published version as yanked, so it is no longer used by new restores
+    fn yank(&self, id: &str, version: &Version, reason: Option<&str>) -> Result<(), RegistryError>;
+
+    /// Marks a package as deprecated, optionally suggesting a package to use instead
+    fn deprecate(
+        &self,
+        id: &str,
+        reason: Option<&str>,
+        replacement: Option<&str>,
+    ) -> Result<(), RegistryError>;
+
+    /// The id a package was renamed to, if `id` is the previous id of a renamed package
+    fn get_alias(&self, id: &str) -> Result<Option<String>, RegistryError>;
+
+    /// Redirects the previous id of a renamed package to its new id
+    fn add_alias(&self, alias: &PackageAlias) -> Result<(), RegistryError>;
+
+    /// The current id of a package, following renames
+    fn resolve_alias(&self, id: &str) -> Result<String, RegistryError> {
+        let mut ids = vec![id.to_string()];
+        while let Some(alias) = self.get_alias(ids.last().unwrap())? {
+            let is_cycle = ids.contains(&alias);
+            ids.push(alias);
+            if is_cycle {
+                return Err(RegistryError::AliasCycle(ids));
+            }
+        }
+
+        Ok(ids.pop().unwrap())
+    }
+
+    /// The versions of a package that may be picked by the resolver, sorted from oldest to newest.
+    /// Yanked versions are left out, unless `locked` already restored them.
+    fn get_resolvable_versions(
+        &self,
+        id: &str,
+        locked: Option<&SharedPackageConfig>,
+    ) -> Result<Vec<PackageVersion>, RegistryError> {
+        let is_locked = |version: &Version| {
+            locked.is_some_and(|locked| {
+                locked
+                    .restored_dependencies
+                    .iter()
+                    .any(|d| d.dependency.id == id && &d.version == version)
+            })
+        };
+
+        let mut versions: Vec<PackageVersion> = self
+            .get_listing(id)?
+            .versions
+            .into_iter()
+            .filter(|v| !v.yanked || is_locked(&v.version))
+            .map(|v| PackageVersion {
+                id: id.to_string(),
+                version: v.version,
+            })
+            .collect();
+
+        versions.sort_by(|a, b| a.version.cmp(&b.version));
+        Ok(versions)
+    }
+}
@@ -0,0 +1,407 @@
+use std::path::PathBuf;
+
+use semver::Version;
+use thiserror::Error;
+
+use crate::{
+    extensions::{
+        additional_metadata::AdditionalPackageMetadataExtensions,
+        package_dependency::PackageDependencyExtensions,
+    },
+    models::{
+        dependency::SharedPackageConfig, extra::DependencyLibType, package::PackageConfig,
+        source::DependencySource,
+    },
+};
+
+use super::{Registry, RegistryError};
+
+/// A problem found before publishing that would make the published package unusable
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum PublishCheckError {
+    #[error("version {1} of package {0} is already published")]
+    AlreadyPublished(String, Version),
+
+    #[error("{0} is required for a {1:?} package")]
+    MissingLink(&'static str, DependencyLibType),
+
+    #[error("{0} is not allowed for a headers only package")]
+    UnexpectedBinary(&'static str),
+
+    #[error("{0} {1:?} is not a valid url")]
+    InvalidLink(&'static str, String),
+
+    #[error("dependency {0} is restored from the local path {1:?}")]
+    LocalPathDependency(String, PathBuf),
+
+    #[error("dependency {0} is not restored, restore before publishing")]
+    UnrestoredDependency(String),
+
+    #[error("the shared package config is for {0} {1}, not the package config")]
+    PackageMismatch(String, Version),
+}
+
+#[derive(Debug, Error)]
+pub enum PublishError {
+    #[error("{} pre-publish check(s) failed", .0.len())]
+    Checks(Vec<PublishCheckError>),
+
+    #[error(transparent)]
+    Registry(#[from] RegistryError),
+}
+
+/// The package sent to a registry when publishing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PublishPayload {
+    pub package: SharedPackageConfig,
+}
+
+impl PublishPayload {
+    pub fn publish(&self, registry: &impl Registry) -> Result<(), RegistryError> {
+        registry.publish(&self.package)
+    }
+}
+
+/// Assembles a [`PublishPayload`] from qpm.json and qpm.shared.json, checking it can be published
+#[derive(Clone, Copy, Debug)]
+pub struct PublishPayloadBuilder<'a> {
+    config: &'a PackageConfig,
+    shared: &'a SharedPackageConfig,
+}
+
+impl<'a> PublishPayloadBuilder<'a> {
+    pub fn new(config: &'a PackageConfig, shared: &'a SharedPackageConfig) -> Self {
+        Self { config, shared }
+    }
+
+    /// Checks that do not need a registry
+    pub fn check(&self) -> Vec<PublishCheckError> {
+        let mut errors = vec![];
+
+        let info = &self.config.info;
+        let shared_info = &self.shared.config.info;
+        if shared_info.id != info.id || shared_info.version != info.version {
+            errors.push(PublishCheckError::PackageMismatch(
+                shared_info.id.clone(),
+                shared_info.version.clone(),
+            ));
+        }
+
+        let metadata = &info.additional_data;
+        let links = [
+            ("soLink", &metadata.so_link),
+            ("staticLink", &metadata.static_link),
+            ("debugSoLink", &metadata.debug_so_link),
+            ("modLink", &metadata.mod_link),
+        ];
+        for (name, link) in links {
+            if let Some(link) = link {
+                if !link.contains("://") {
+                    errors.push(PublishCheckError::InvalidLink(name, link.clone()));
+                }
+            }
+        }
+
+        let lib_type = metadata.get_lib_type();
+        match lib_type {
+            DependencyLibType::HeaderOnly => {
+                let binaries = [
+                    ("soLink", &metadata.so_link),
+                    ("staticLink", &metadata.static_link),
+                    ("debugSoLink", &metadata.debug_so_link),
+                ];
+                errors.extend(
+                    binaries
+                        .into_iter()
+                        .filter(|(_, link)| link.is_some())
+                        .map(|(name, _)| PublishCheckError::UnexpectedBinary(name)),
+                );
+            }
+            DependencyLibType::Shared if metadata.so_link.is_none() => {
+                errors.push(PublishCheckError::MissingLink("soLink", lib_type));
+            }
+            DependencyLibType::Static if metadata.static_link.is_none() => {
+                errors.push(PublishCheckError::MissingLink("staticLink", lib_type));
+            }
+            _ => {}
+        }
+
+        for dependency in &self.config.dependencies {
+            if let DependencySource::Path { path } = dependency.get_source() {
+                errors.push(PublishCheckError::LocalPathDependency(
+                    dependency.id.clone(),
+                    path,
+                ));
+            }
+
+            // renamed packages are restored under their new id, overrides may replace the range
+            let is_restored = self.shared.restored_dependencies.iter().any(|d| {
+                let declared_id = d.declared_id.as_ref().unwrap_or(&d.dependency.id);
+                let range = d
+                    .applied_override
+                    .as_ref()
+                    .and_then(|o| o.version.as_ref())
+                    .unwrap_or(&dependency.version_range);
+                declared_id == &dependency.id && range.matches(&d.version)
+            });
+            if !is_restored {
+                errors.push(PublishCheckError::UnrestoredDependency(
+                    dependency.id.clone(),
+                ));
+            }
+        }
+
+        errors
+    }
+
+    /// Checks against the registry that is published to
+    pub fn check_registry(
+        &self,
+        registry: &impl Registry,
+    ) -> Result<Vec<PublishCheckError>, RegistryError> {
+        let info = &self.config.info;
+        let versions = match registry.get_versions(&info.id) {
+            Ok(versions) => versions,
+            // first publish of this package
+            Err(RegistryError::PackageNotFound(_)) => vec![],
+            Err(e) => return Err(e),
+        };
+
+        let mut errors = vec![];
+        if versions.iter().any(|v| v.version == info.version) {
+            errors.push(PublishCheckError::AlreadyPublished(
+                info.id.clone(),
+                info.version.clone(),
+            ));
+        }
+
+        Ok(errors)
+    }
+
+    /// Runs all checks and assembles the payload if they pass
+    pub fn build(&self, registry: &impl Registry) -> Result<PublishPayload, PublishError> {
+        let mut errors = self.check();
+        errors.extend(self.check_registry(registry)?);
+        if !errors.is_empty() {
+            return Err(PublishError::Checks(errors));
+        }
+
+        Ok(PublishPayload {
+            package: SharedPackageConfig {
+                config: self.config.clone(),
+                restored_dependencies: self.shared.restored_dependencies.clone(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::VersionReq;
+
+    use super::*;
+    use crate::{
+        models::{
+            dependency::{Dependency, SharedDependency},
+            extra::AdditionalPackageMetadata,
+            package::{PackageDependency, PackageMetadata},
+        },
+        registry::local::LocalRegistry,
+    };
+
+    fn package(dependencies: Vec<PackageDependency>) -> PackageConfig {
+        PackageConfig {
+            info: PackageMetadata {
+                name: "root".to_string(),
+                id: "root".to_string(),
+                version: Version::new(1, 0, 0),
+                url: None,
+                publisher_key: None,
+                additional_data: AdditionalPackageMetadata {
+                    headers_only: Some(true),
+                    ..Default::default()
+                },
+            },
+            dependencies,
+            ..Default::default()
+        }
+    }
+
+    fn dependency(id: &str, range: &str) -> PackageDependency {
+        PackageDependency {
+            id: id.to_string(),
+            version_range: VersionReq::parse(range).unwrap(),
+            source: None,
+            additional_data: Default::default(),
+        }
+    }
+
+    #[allow(deprecated)]
+    fn restored(id: &str, version: &str, declared_id: Option<&str>) -> SharedDependency {
+        SharedDependency {
+            dependency: Dependency {
+                id: id.to_string(),
+                version_range: VersionReq::STAR,
+                additional_data: Default::default(),
+            },
+            version: Version::parse(version).unwrap(),
+            checksums: Default::default(),
+            publisher_key: None,
+            source: None,
+            required_by: vec!["root".to_string()],
+            declared_id: declared_id.map(str::to_string),
+            applied_override: None,
+        }
+    }
+
+    fn check(
+        config: &PackageConfig,
+        restored_dependencies: Vec<SharedDependency>,
+    ) -> Vec<PublishCheckError> {
+        let shared = SharedPackageConfig {
+            config: config.clone(),
+            restored_dependencies,
+        };
+        PublishPayloadBuilder::new(config, &shared).check()
+    }
+
+    #[test]
+    fn finds_renamed_dependencies_by_declared_id() {
+        let config = package(vec![dependency("old-name", "^1.0.0")]);
+
+        let errors = check(
+            &config,
+            vec![restored("new-name", "1.2.0", Some("old-name"))],
+        );
+        assert_eq!(errors, vec![]);
+
+        let unrestored = vec![PublishCheckError::UnrestoredDependency(
+            "old-name".to_string(),
+        )];
+        let errors = check(&config, vec![restored("new-name", "1.2.0", None)]);
+        assert_eq!(errors, unrestored);
+
+        let errors = check(
+            &config,
+            vec![restored("new-name", "2.0.0", Some("old-name"))],
+        );
+        assert_eq!(errors, unrestored);
+    }
+
+    #[test]
+    fn requires_links_of_the_declared_lib_type() {
+        let with_metadata = |additional_data| {
+            let mut config = package(vec![]);
+            config.info.additional_data = additional_data;
+            check(&config, vec![])
+        };
+
+        assert_eq!(
+            with_metadata(Default::default()),
+            vec![PublishCheckError::MissingLink(
+                "soLink",
+                DependencyLibType::Shared
+            )]
+        );
+        #[allow(deprecated)]
+        let static_linking = AdditionalPackageMetadata {
+            static_linking: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            with_metadata(static_linking),
+            vec![PublishCheckError::MissingLink(
+                "staticLink",
+                DependencyLibType::Static
+            )]
+        );
+        assert_eq!(
+            with_metadata(AdditionalPackageMetadata {
+                headers_only: Some(true),
+                so_link: Some("https://files.test/root.so".to_string()),
+                debug_so_link: Some("https://files.test/debug.so".to_string()),
+                ..Default::default()
+            }),
+            vec![
+                PublishCheckError::UnexpectedBinary("soLink"),
+                PublishCheckError::UnexpectedBinary("debugSoLink"),
+            ]
+        );
+        assert_eq!(
+            with_metadata(AdditionalPackageMetadata {
+                so_link: Some("root.so".to_string()),
+                mod_link: Some("https://files.test/root.qmod".to_string()),
+                ..Default::default()
+            }),
+            vec![PublishCheckError::InvalidLink(
+                "soLink",
+                "root.so".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn rejects_published_versions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let registry = LocalRegistry::new(temp_dir.path());
+        let config = package(vec![]);
+        let shared = SharedPackageConfig {
+            config: config.clone(),
+            restored_dependencies: vec![],
+        };
+        let builder = PublishPayloadBuilder::new(&config, &shared);
+
+        let payload = builder.build(&registry).unwrap();
+        assert_eq!(payload.package, shared);
+        payload.publish(&registry).unwrap();
+
+        match builder.build(&registry) {
+            Err(PublishError::Checks(errors)) => assert_eq!(
+                errors,
+                vec![PublishCheckError::AlreadyPublished(
+                    "root".to_string(),
+                    Version::new(1, 0, 0)
+                )]
+            ),
+            result => panic!("expected failed checks, got {result:?}"),
+        }
+
+        // the shared config has to be restored for the published version
+        let mut next = config.clone();
+        next.info.version = Version::new(1, 1, 0);
+        let errors = PublishPayloadBuilder::new(&next, &shared).check();
+        assert_eq!(
+            errors,
+            vec![PublishCheckError::PackageMismatch(
+                "root".to_string(),
+                Version::new(1, 0, 0)
+            )]
+        );
+    }
+
+    #[test]
+    fn rejects_path_dependencies() {
+        let mut legacy = dependency("legacy", "^1.0.0");
+        legacy.additional_data.local_path = Some("../legacy".to_string());
+        let mut path = dependency("path", "^1.0.0");
+        path.source = Some(DependencySource::Path {
+            path: "../path".into(),
+        });
+        let config = package(vec![legacy, path]);
+
+        let errors = check(
+            &config,
+            vec![
+                restored("legacy", "1.0.0", None),
+                restored("path", "1.0.0", None),
+            ],
+        );
+        assert_eq!(
+            errors,
+            vec![
+                PublishCheckError::LocalPathDependency("legacy".to_string(), "../legacy".into()),
+                PublishCheckError::LocalPathDependency("path".to_string(), "../path".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,925 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::{Component, Path, PathBuf},
+};
+
+use semver::{Version, VersionReq};
+use thiserror::Error;
+
+use crate::{
+    extensions::{
+        package_dependency::PackageDependencyExtensions,
+        shared_package_config::SharedPackageConfigExtensions,
+    },
+    models::{
+        backend::PackageVersion,
+        dependency::{Dependency, SharedDependency, SharedPackageConfig},
+        package::{DependencyOverride, PackageConfig, PackageDependency},
+        source::{DependencySource, GitReference},
+        user_config::ResolutionStrategy,
+    },
+    path_dependency::{canonicalize, get_dependency_path, load_local_package, PathDependencyError},
+    registry::{Registry, RegistryError},
+    signing::{check_publisher_key, SignatureError},
+    source_loader::{LoadedSource, SourceError, SourceLoader},
+};
+
+#[derive(Debug, Error)]
+pub enum ResolveError {
+    #[error("no version of {0} matches {}", .1.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    NoMatchingVersion(String, Vec<VersionReq>),
+
+    #[error("{id} {version} was picked but {range} is required by {required_by}")]
+    Conflict {
+        id: String,
+        version: Version,
+        range: VersionReq,
+        required_by: String,
+    },
+
+    #[error("{0} is restored from a git repository or url, but no source loader was given")]
+    NoSourceLoader(String),
+
+    #[error("the source of {expected} contains {found}")]
+    SourceIdMismatch { expected: String, found: String },
+
+    #[error("{id} is restored from {registry}, but only the default registry is supported")]
+    UnsupportedRegistry { id: String, registry: String },
+
+    #[error(transparent)]
+    Registry(#[from] RegistryError),
+
+    #[error(transparent)]
+    Source(#[from] SourceError),
+
+    #[error(transparent)]
+    Signature(#[from] SignatureError),
+
+    #[error(transparent)]
+    PathDependency(#[from] PathDependencyError),
+}
+
+/// Something that did not stop resolution but should be reported
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolveWarning {
+    /// A dependency was declared with the previous id of a renamed package
+    Renamed {
+        id: String,
+        renamed_to: String,
+        required_by: String,
+    },
+}
+
+impl std::fmt::Display for ResolveWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolveWarning::Renamed {
+                id,
+                renamed_to,
+                required_by,
+            } => write!(
+                f,
+                "{required_by} depends on {id} which was renamed to {renamed_to}, use {renamed_to} instead"
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Resolution {
+    pub shared: SharedPackageConfig,
+    pub warnings: Vec<ResolveWarning>,
+}
+
+/// A dependency waiting to be resolved
+struct Requirement {
+    id: String,
+    range: VersionReq,
+    required_by: String,
+    /// The directory of a path dependency
+    path: Option<PathBuf>,
+    /// The git repository, url or other registry to restore the dependency from instead of the default registry
+    source: Option<DependencySource>,
+    applied_override: Option<DependencyOverride>,
+}
+
+impl Requirement {
+    /// `manifest_dir` is the directory of the local package declaring the dependency,
+    /// path dependencies of packages from a registry are restored from the registry instead
+    fn new(dependency: &PackageDependency, required_by: &str, manifest_dir: Option<&Path>) -> Self {
+        let source = dependency.get_source();
+        Self {
+            id: dependency.id.clone(),
+            range: dependency.version_range.clone(),
+            required_by: required_by.to_string(),
+            path: manifest_dir.and_then(|dir| get_dependency_path(dir, dependency)),
+            source: (!matches!(
+                source,
+                DependencySource::Path { .. } | DependencySource::Registry { registry: None }
+            ))
+            .then_some(source),
+            applied_override: None,
+        }
+    }
+
+    /// Replaces the requirement with the first override of the root package matching it.
+    /// Override paths are relative to `root_dir`.
+    fn apply_override(&mut self, overrides: &[DependencyOverride], root_dir: &Path) {
+        let Some(dependency_override) = overrides.iter().find(|o| {
+            o.id == self.id && !o.version_range.as_ref().is_some_and(|r| r != &self.range)
+        }) else {
+            return;
+        };
+
+        if let Some(version) = &dependency_override.version {
+            self.range = version.clone();
+        }
+        match &dependency_override.source {
+            Some(DependencySource::Path { path }) => {
+                self.path = Some(root_dir.join(path));
+                self.source = None;
+            }
+            Some(DependencySource::Registry { registry: None }) => {
+                self.path = None;
+                self.source = None;
+            }
+            Some(source) => {
+                self.path = None;
+                self.source = Some(source.clone());
+            }
+            None => {}
+        }
+        self.applied_override = Some(dependency_override.clone());
+    }
+}
+
+/// The resolvable versions of every package listed so far, `None` for packages the registry does not have
+type VersionCache = BTreeMap<String, Option<Vec<PackageVersion>>>;
+
+struct Resolved {
+    version: Version,
+    range: VersionReq,
+    config: PackageConfig,
+    source: Option<DependencySource>,
+    applied_override: Option<DependencyOverride>,
+    /// The previous id a renamed package was first required with
+    declared_id: Option<String>,
+    required_by: Vec<String>,
+}
+
+/// Resolves the dependencies of a package against a registry.
+///
+/// The newest version matching every range that requires a package is picked, or the oldest with
+/// [`ResolutionStrategy::Lowest`]. Versions restored by the locked config are preferred as long as they still match.
+/// Path dependencies are loaded from disk instead, relative to the directory of the qpm.json declaring them,
+/// and git and url dependencies from their source, keeping the commit a git source was locked to.
+pub struct Resolver<'a, R: Registry> {
+    registry: &'a R,
+    sources: Option<&'a dyn SourceLoader>,
+    locked: Option<&'a SharedPackageConfig>,
+    manifest_dir: Option<&'a Path>,
+    strategy: ResolutionStrategy,
+}
+
+impl<'a, R: Registry> Resolver<'a, R> {
+    pub fn new(registry: &'a R) -> Self {
+        Self {
+            registry,
+            sources: None,
+            locked: None,
+            manifest_dir: None,
+            strategy: ResolutionStrategy::default(),
+        }
+    }
+
+    /// Loads the dependencies restored from a git repository or url
+    pub fn with_sources(mut self, sources: &'a dyn SourceLoader) -> Self {
+        self.sources = Some(sources);
+        self
+    }
+
+    /// Prefers the versions restored in a previous qpm.shared.json
+    pub fn with_locked(mut self, locked: &'a SharedPackageConfig) -> Self {
+        self.locked = Some(locked);
+        self
+    }
+
+    /// The directory of the qpm.json being resolved, which path dependencies are relative to.
+    /// The current directory is used otherwise.
+    pub fn with_manifest_dir(mut self, manifest_dir: &'a Path) -> Self {
+        self.manifest_dir = Some(manifest_dir);
+        self
+    }
+
+    /// Whether the newest or oldest matching versions are picked
+    pub fn with_strategy(mut self, strategy: ResolutionStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn resolve(&self, config: &PackageConfig) -> Result<Resolution, ResolveError> {
+        // ranges found to conflict with a previous pick, taken into account from the start on the next attempt
+        let mut constraints: BTreeMap<String, Vec<VersionReq>> = BTreeMap::new();
+        // listed once per package, every attempt and requirement reuses the listing
+        let mut versions = VersionCache::new();
+
+        loop {
+            match self.try_resolve(config, &constraints, &mut versions) {
+                Err(ResolveError::Conflict {
+                    id,
+                    version,
+                    range,
+                    required_by,
+                }) => {
+                    let ranges = constraints.entry(id.clone()).or_default();
+                    if ranges.contains(&range) {
+                        return Err(ResolveError::Conflict {
+                            id,
+                            version,
+                            range,
+                            required_by,
+                        });
+                    }
+                    ranges.push(range);
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn try_resolve(
+        &self,
+        config: &PackageConfig,
+        constraints: &BTreeMap<String, Vec<VersionReq>>,
+        versions: &mut VersionCache,
+    ) -> Result<Resolution, ResolveError> {
+        let mut warnings = vec![];
+        let mut resolved: Vec<(String, Resolved)> = vec![];
+
+        let root_dir = self.manifest_dir.unwrap_or(Path::new(""));
+        let mut queue: VecDeque<Requirement> = config
+            .dependencies
+            .iter()
+            .map(|d| Requirement::new(d, &config.info.id, Some(root_dir)))
+            .collect();
+
+        while let Some(mut requirement) = queue.pop_front() {
+            requirement.apply_override(&config.overrides, root_dir);
+
+            // packages that are not published can't be renamed
+            let id = match (&requirement.path, &requirement.source) {
+                (None, None) => self.get_current_id(&requirement.id, &resolved, versions)?,
+                _ => requirement.id.clone(),
+            };
+            let declared_id = (id != requirement.id).then(|| requirement.id.clone());
+            if let Some(declared_id) = &declared_id {
+                warnings.push(ResolveWarning::Renamed {
+                    id: declared_id.clone(),
+                    renamed_to: id.clone(),
+                    required_by: requirement.required_by.clone(),
+                });
+            }
+
+            if let Some((_, existing)) = resolved.iter_mut().find(|(i, _)| *i == id) {
+                if existing.declared_id.is_none() {
+                    existing.declared_id = declared_id;
+                }
+                if !existing.required_by.contains(&requirement.required_by) {
+                    existing.required_by.push(requirement.required_by.clone());
+                }
+                if !requirement.range.matches(&existing.version) {
+                    return Err(ResolveError::Conflict {
+                        id,
+                        version: existing.version.clone(),
+                        range: requirement.range,
+                        required_by: requirement.required_by,
+                    });
+                }
+
+                for comparator in requirement.range.comparators {
+                    if !existing.range.comparators.contains(&comparator) {
+                        existing.range.comparators.push(comparator);
+                    }
+                }
+                continue;
+            }
+
+            if let Some(path) = &requirement.path {
+                let local = load_local_package(path, &id, &requirement.range)?;
+                self.check_locked_key(&id, &local.config)?;
+
+                queue.extend(
+                    local
+                        .config
+                        .dependencies
+                        .iter()
+                        .filter(|d| !d.additional_data.is_private.unwrap_or(false))
+                        .map(|d| Requirement::new(d, &id, Some(&local.dir))),
+                );
+
+                let path = get_relative_path(root_dir, &local.dir);
+                resolved.push((
+                    id,
+                    Resolved {
+                        version: local.config.info.version.clone(),
+                        range: requirement.range,
+                        config: local.config,
+                        source: Some(DependencySource::Path { path }),
+                        applied_override: requirement.applied_override,
+                        declared_id,
+                        required_by: vec![requirement.required_by],
+                    },
+                ));
+                continue;
+            }
+
+            if let Some(source) = &requirement.source {
+                if let DependencySource::Registry {
+                    registry: Some(registry),
+                } = source
+                {
+                    return Err(ResolveError::UnsupportedRegistry {
+                        id,
+                        registry: registry.clone(),
+                    });
+                }
+
+                let loaded =
+                    self.load_source(&id, source, requirement.applied_override.as_ref())?;
+                if loaded.config.info.id != id {
+                    return Err(ResolveError::SourceIdMismatch {
+                        expected: id,
+                        found: loaded.config.info.id,
+                    });
+                }
+                if !requirement.range.matches(&loaded.config.info.version) {
+                    return Err(ResolveError::NoMatchingVersion(id, vec![requirement.range]));
+                }
+                self.check_locked_key(&id, &loaded.config)?;
+
+                queue.extend(
+                    loaded
+                        .config
+                        .dependencies
+                        .iter()
+                        .filter(|d| !d.additional_data.is_private.unwrap_or(false))
+                        .map(|d| Requirement::new(d, &id, None)),
+                );
+
+                resolved.push((
+                    id,
+                    Resolved {
+                        version: loaded.config.info.version.clone(),
+                        range: requirement.range,
+                        config: loaded.config,
+                        source: Some(loaded.source),
+                        applied_override: requirement.applied_override,
+                        declared_id,
+                        required_by: vec![requirement.required_by],
+                    },
+                ));
+                continue;
+            }
+
+            let mut ranges = vec![requirement.range.clone()];
+            ranges.extend(constraints.get(&id).into_iter().flatten().cloned());
+
+            let version = self.pick_version(&id, &ranges, versions)?;
+            let package = self.registry.get_package(&id, &version)?;
+            self.check_locked_key(&id, &package)?;
+
+            // private dependencies of a dependency are not restored with it
+            queue.extend(
+                package
+                    .dependencies
+                    .iter()
+                    .filter(|d| !d.additional_data.is_private.unwrap_or(false))
+                    .map(|d| Requirement::new(d, &id, None)),
+            );
+
+            resolved.push((
+                id,
+                Resolved {
+                    version,
+                    range: requirement.range,
+                    config: package,
+                    source: None,
+                    applied_override: requirement.applied_override,
+                    declared_id,
+                    required_by: vec![requirement.required_by],
+                },
+            ));
+        }
+
+        let restored_dependencies = resolved
+            .into_iter()
+            .map(|(id, resolved)| {
+                // keep the digests recorded when this version was restored from the same source before
+                let checksums = self
+                    .get_locked(&id)
+                    .filter(|locked| {
+                        locked.version == resolved.version && locked.source == resolved.source
+                    })
+                    .map(|locked| locked.checksums.clone())
+                    .unwrap_or_default();
+
+                let info = resolved.config.info;
+                SharedDependency {
+                    #[allow(deprecated)]
+                    dependency: Dependency {
+                        id,
+                        version_range: resolved.range,
+                        additional_data: info.additional_data,
+                    },
+                    version: resolved.version,
+                    checksums,
+                    publisher_key: info.publisher_key,
+                    source: resolved.source,
+                    applied_override: resolved.applied_override,
+                    declared_id: resolved.declared_id,
+                    required_by: resolved.required_by,
+                }
+            })
+            .collect();
+
+        Ok(Resolution {
+            shared: SharedPackageConfig {
+                config: config.clone(),
+                restored_dependencies,
+            },
+            warnings,
+        })
+    }
+
+    /// The current id of a required package. Renames are only looked up if the id is neither resolved
+    /// already nor has any versions, so resolving does not query the aliases of every dependency.
+    fn get_current_id(
+        &self,
+        id: &str,
+        resolved: &[(String, Resolved)],
+        versions: &mut VersionCache,
+    ) -> Result<String, ResolveError> {
+        if let Some((current_id, _)) = resolved
+            .iter()
+            .find(|(i, r)| i == id || r.declared_id.as_deref() == Some(id))
+        {
+            return Ok(current_id.clone());
+        }
+
+        if self
+            .get_versions(id, versions)?
+            .is_some_and(|versions| !versions.is_empty())
+        {
+            return Ok(id.to_string());
+        }
+        Ok(self.registry.resolve_alias(id)?)
+    }
+
+    /// The resolvable versions of a package, listed once per resolve. `None` if the registry has no such package
+    fn get_versions<'v>(
+        &self,
+        id: &str,
+        versions: &'v mut VersionCache,
+    ) -> Result<Option<&'v [PackageVersion]>, ResolveError> {
+        if !versions.contains_key(id) {
+            let listed = match self.registry.get_resolvable_versions(id, self.locked) {
+                Ok(listed) => Some(listed),
+                Err(RegistryError::PackageNotFound(_)) => None,
+                Err(e) => return Err(e.into()),
+            };
+            versions.insert(id.to_string(), listed);
+        }
+
+        Ok(versions[id].as_deref())
+    }
+
+    /// Loads a git or url source, at the commit a git source was locked to if it is still declared the same way
+    fn load_source(
+        &self,
+        id: &str,
+        source: &DependencySource,
+        applied_override: Option<&DependencyOverride>,
+    ) -> Result<LoadedSource, ResolveError> {
+        let sources = self
+            .sources
+            .ok_or_else(|| ResolveError::NoSourceLoader(id.to_string()))?;
+
+        let source = match self.get_locked_commit(id, source, applied_override) {
+            Some(commit) => source.clone().with_commit(commit),
+            None => source.clone(),
+        };
+        Ok(sources.load(&source)?)
+    }
+
+    /// The commit a git source was locked to, `None` if the url, sub folder or reference changed since.
+    /// The reference is compared with the override or root dependency that declared the source back then,
+    /// sources of transitive dependencies are pinned by the version of the package declaring them.
+    fn get_locked_commit(
+        &self,
+        id: &str,
+        source: &DependencySource,
+        applied_override: Option<&DependencyOverride>,
+    ) -> Option<&'a str> {
+        let locked_config = self.locked?;
+        let locked = self.get_locked(id)?;
+        let Some(DependencySource::Git {
+            reference: Some(GitReference::Rev(commit)),
+            ..
+        }) = &locked.source
+        else {
+            return None;
+        };
+
+        if locked.source.as_ref() != Some(&source.clone().with_commit(commit))
+            || locked.applied_override.is_some() != applied_override.is_some()
+        {
+            return None;
+        }
+
+        let declared = match &locked.applied_override {
+            Some(locked_override) => locked_override.source.clone(),
+            None => locked_config
+                .get_declared_dependency(locked)
+                .map(|d| d.get_source()),
+        };
+        declared
+            .is_none_or(|declared| &declared == source)
+            .then_some(commit.as_str())
+    }
+
+    fn get_locked(&self, id: &str) -> Option<&'a SharedDependency> {
+        self.locked?
+            .restored_dependencies
+            .iter()
+            .find(|d| d.dependency.id == id)
+    }
+
+    /// Checks that the publisher key of a package from any source has not changed since it was locked
+    fn check_locked_key(&self, id: &str, package: &PackageConfig) -> Result<(), ResolveError> {
+        let pinned_key = self.get_locked(id).and_then(|d| d.publisher_key.as_deref());
+        check_publisher_key(id, pinned_key, package.info.publisher_key.as_deref())?;
+        Ok(())
+    }
+
+    fn pick_version(
+        &self,
+        id: &str,
+        ranges: &[VersionReq],
+        versions: &mut VersionCache,
+    ) -> Result<Version, ResolveError> {
+        let matches = |version: &&Version| ranges.iter().all(|r| r.matches(version));
+
+        let locked_version = self.get_locked(id).map(|d| &d.version);
+
+        let versions = self
+            .get_versions(id, versions)?
+            .ok_or_else(|| RegistryError::PackageNotFound(id.to_string()))?;
+        if let Some(locked_version) = locked_version {
+            if matches(&locked_version) && versions.iter().any(|v| &v.version == locked_version) {
+                return Ok(locked_version.clone());
+            }
+        }
+
+        let mut versions = versions.iter().map(|v| &v.version);
+        let version = match self.strategy {
+            ResolutionStrategy::Highest => versions.rev().find(matches),
+            ResolutionStrategy::Lowest => versions.find(matches),
+        };
+        version
+            .cloned()
+            .ok_or_else(|| ResolveError::NoMatchingVersion(id.to_string(), ranges.to_vec()))
+    }
+}
+
+/// The path of a local package relative to the root package, its absolute path if there is no relative path
+fn get_relative_path(root_dir: &Path, dir: &Path) -> PathBuf {
+    let Ok(root_dir) = canonicalize(root_dir) else {
+        return dir.to_path_buf();
+    };
+
+    let mut root_components = root_dir.components().peekable();
+    let mut dir_components = dir.components().peekable();
+    while let (Some(a), Some(b)) = (root_components.peek(), dir_components.peek()) {
+        if a != b {
+            break;
+        }
+        root_components.next();
+        dir_components.next();
+    }
+
+    // on windows, paths on different drives have no relative path
+    if dir_components
+        .peek()
+        .is_some_and(|c| matches!(c, Component::Prefix(_)))
+    {
+        return dir.to_path_buf();
+    }
+
+    root_components
+        .map(|_| Component::ParentDir)
+        .chain(dir_components)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{
+        models::{backend::PackageAlias, package::PackageMetadata},
+        registry::local::LocalRegistry,
+    };
+
+    /// An empty registry, removed with the returned directory
+    fn get_registry() -> (TempDir, LocalRegistry) {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = LocalRegistry::new(dir.path());
+        (dir, registry)
+    }
+
+    fn dependency(id: &str, range: &str) -> PackageDependency {
+        PackageDependency {
+            id: id.to_string(),
+            version_range: VersionReq::parse(range).unwrap(),
+            source: None,
+            additional_data: Default::default(),
+        }
+    }
+
+    fn package(id: &str, version: &str, dependencies: Vec<PackageDependency>) -> PackageConfig {
+        PackageConfig {
+            info: PackageMetadata {
+                name: id.to_string(),
+                id: id.to_string(),
+                version: Version::parse(version).unwrap(),
+                url: None,
+                publisher_key: None,
+                additional_data: Default::default(),
+            },
+            dependencies,
+            ..Default::default()
+        }
+    }
+
+    fn publish(registry: &LocalRegistry, config: PackageConfig) {
+        let shared = SharedPackageConfig {
+            config,
+            restored_dependencies: vec![],
+        };
+        registry.publish(&shared).unwrap();
+    }
+
+    fn get_version<'a>(resolution: &'a Resolution, id: &str) -> Option<&'a Version> {
+        resolution
+            .shared
+            .restored_dependencies
+            .iter()
+            .find(|d| d.dependency.id == id)
+            .map(|d| &d.version)
+    }
+
+    #[test]
+    fn picks_newest_matching_version() {
+        let (_dir, registry) = get_registry();
+        for version in ["1.0.0", "1.1.0", "2.0.0"] {
+            publish(&registry, package("a", version, vec![]));
+        }
+        let root = package("root", "1.0.0", vec![dependency("a", "^1.0.0")]);
+
+        let resolution = Resolver::new(&registry).resolve(&root).unwrap();
+        assert_eq!(get_version(&resolution, "a"), Some(&Version::new(1, 1, 0)));
+
+        let lowest = Resolver::new(&registry)
+            .with_strategy(ResolutionStrategy::Lowest)
+            .resolve(&root)
+            .unwrap();
+        assert_eq!(get_version(&lowest, "a"), Some(&Version::new(1, 0, 0)));
+    }
+
+    #[test]
+    fn retries_with_conflicting_ranges() {
+        let (_dir, registry) = get_registry();
+        publish(&registry, package("a", "1.0.0", vec![]));
+        publish(&registry, package("a", "1.1.0", vec![]));
+        publish(
+            &registry,
+            package("b", "1.0.0", vec![dependency("a", "=1.0.0")]),
+        );
+        let root = package(
+            "root",
+            "1.0.0",
+            vec![dependency("a", "^1.0.0"), dependency("b", "^1.0.0")],
+        );
+
+        let resolution = Resolver::new(&registry).resolve(&root).unwrap();
+        assert_eq!(get_version(&resolution, "a"), Some(&Version::new(1, 0, 0)));
+        assert_eq!(get_version(&resolution, "b"), Some(&Version::new(1, 0, 0)));
+    }
+
+    #[test]
+    fn fails_on_unresolvable_conflict() {
+        let (_dir, registry) = get_registry();
+        publish(&registry, package("a", "1.0.0", vec![]));
+        publish(&registry, package("a", "1.1.0", vec![]));
+        publish(
+            &registry,
+            package("b", "1.0.0", vec![dependency("a", "=1.1.0")]),
+        );
+        let root = package(
+            "root",
+            "1.0.0",
+            vec![dependency("a", "=1.0.0"), dependency("b", "^1.0.0")],
+        );
+
+        let result = Resolver::new(&registry).resolve(&root);
+        assert!(matches!(result, Err(ResolveError::NoMatchingVersion(id, _)) if id == "a"));
+    }
+
+    #[test]
+    fn follows_renamed_packages() {
+        let (_dir, registry) = get_registry();
+        publish(&registry, package("new", "1.0.0", vec![]));
+        registry
+            .add_alias(&PackageAlias {
+                alias: "old".to_string(),
+                id: "new".to_string(),
+            })
+            .unwrap();
+        let mut declared = dependency("old", "^1.0.0");
+        declared.additional_data.is_private = Some(true);
+        let root = package("root", "1.0.0", vec![declared]);
+
+        let resolution = Resolver::new(&registry).resolve(&root).unwrap();
+        let restored = &resolution.shared.restored_dependencies;
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].dependency.id, "new");
+        assert_eq!(restored[0].declared_id.as_deref(), Some("old"));
+        assert_eq!(
+            resolution.warnings,
+            vec![ResolveWarning::Renamed {
+                id: "old".to_string(),
+                renamed_to: "new".to_string(),
+                required_by: "root".to_string(),
+            }]
+        );
+
+        // the modifier of the declared id still applies to the renamed package
+        let modifier = resolution.shared.get_dependency_modifier(&restored[0]);
+        assert_eq!(modifier.and_then(|m| m.is_private), Some(true));
+    }
+
+    /// Resolves every branch to `head` and loads the same package from every source
+    struct FakeSources {
+        head: &'static str,
+    }
+
+    impl SourceLoader for FakeSources {
+        fn load(&self, source: &DependencySource) -> Result<LoadedSource, SourceError> {
+            let commit = match source {
+                DependencySource::Git {
+                    reference: Some(GitReference::Rev(rev)),
+                    ..
+                } => rev.as_str(),
+                _ => self.head,
+            };
+            Ok(LoadedSource {
+                config: package("a", "1.0.0", vec![]),
+                source: source.clone().with_commit(commit),
+            })
+        }
+    }
+
+    #[test]
+    fn keeps_locked_commit_until_the_source_changes() {
+        let (_dir, registry) = get_registry();
+        let git = |branch: &str| DependencySource::Git {
+            url: "https://github.com/owner/a".to_string(),
+            reference: Some(GitReference::Branch(branch.to_string())),
+            sub_folder: None,
+        };
+        let mut declared = dependency("a", "^1.0.0");
+        declared.source = Some(git("main"));
+        let root = package("root", "1.0.0", vec![declared]);
+
+        let locked = Resolver::new(&registry)
+            .with_sources(&FakeSources { head: "1111" })
+            .resolve(&root)
+            .unwrap()
+            .shared;
+        assert_eq!(
+            locked.restored_dependencies[0].source,
+            Some(git("main").with_commit("1111"))
+        );
+
+        // the branch moved on, but the lockfile keeps the commit
+        let resolution = Resolver::new(&registry)
+            .with_sources(&FakeSources { head: "2222" })
+            .with_locked(&locked)
+            .resolve(&root)
+            .unwrap();
+        assert_eq!(
+            resolution.shared.restored_dependencies[0].source,
+            Some(git("main").with_commit("1111"))
+        );
+
+        // a different branch is resolved again
+        let mut changed = root.clone();
+        changed.dependencies[0].source = Some(git("dev"));
+        let resolution = Resolver::new(&registry)
+            .with_sources(&FakeSources { head: "2222" })
+            .with_locked(&locked)
+            .resolve(&changed)
+            .unwrap();
+        assert_eq!(
+            resolution.shared.restored_dependencies[0].source,
+            Some(git("dev").with_commit("2222"))
+        );
+    }
+
+    #[test]
+    fn rejects_dependencies_from_other_registries() {
+        let (_dir, registry) = get_registry();
+        publish(&registry, package("a", "1.0.0", vec![]));
+
+        let mut declared = dependency("a", "^1.0.0");
+        declared.source = Some(DependencySource::Registry {
+            registry: Some("https://other.test".to_string()),
+        });
+        let root = package("root", "1.0.0", vec![declared]);
+        assert!(matches!(
+            Resolver::new(&registry).resolve(&root),
+            Err(ResolveError::UnsupportedRegistry { id, registry })
+                if id == "a" && registry == "https://other.test"
+        ));
+
+        // the default registry may be named explicitly
+        let mut declared = dependency("a", "^1.0.0");
+        declared.source = Some(DependencySource::Registry { registry: None });
+        let root = package("root", "1.0.0", vec![declared]);
+        let resolution = Resolver::new(&registry).resolve(&root).unwrap();
+        assert_eq!(get_version(&resolution, "a"), Some(&Version::new(1, 0, 0)));
+    }
+
+    #[test]
+    fn rejects_changed_publisher_keys_of_every_source() {
+        let (_dir, registry) = get_registry();
+        let mut declared = dependency("a", "^1.0.0");
+        declared.source = Some(DependencySource::Url {
+            url: "https://example.com/a.zip".to_string(),
+            sub_folder: None,
+        });
+        let root = package("root", "1.0.0", vec![declared]);
+
+        let mut locked = Resolver::new(&registry)
+            .with_sources(&FakeSources { head: "1111" })
+            .resolve(&root)
+            .unwrap()
+            .shared;
+        locked.restored_dependencies[0].publisher_key = Some("abcd".to_string());
+
+        assert!(matches!(
+            Resolver::new(&registry)
+                .with_sources(&FakeSources { head: "1111" })
+                .with_locked(&locked)
+                .resolve(&root),
+            Err(ResolveError::Signature(SignatureError::KeyChanged { id, .. })) if id == "a"
+        ));
+    }
+
+    #[test]
+    fn applies_overrides_to_transitive_dependencies() {
+        let (_dir, registry) = get_registry();
+        publish(
+            &registry,
+            package("a", "1.0.0", vec![dependency("b", "^1.0.0")]),
+        );
+        publish(&registry, package("b", "1.0.0", vec![]));
+        publish(&registry, package("b", "2.0.0", vec![]));
+
+        let dependency_override = DependencyOverride {
+            id: "b".to_string(),
+            version_range: Some(VersionReq::parse("^1.0.0").unwrap()),
+            version: Some(VersionReq::parse("^2.0.0").unwrap()),
+            source: None,
+        };
+        let mut root = package("root", "1.0.0", vec![dependency("a", "^1.0.0")]);
+        root.overrides.push(dependency_override.clone());
+
+        let resolution = Resolver::new(&registry).resolve(&root).unwrap();
+        assert_eq!(get_version(&resolution, "b"), Some(&Version::new(2, 0, 0)));
+        let b = resolution
+            .shared
+            .restored_dependencies
+            .iter()
+            .find(|d| d.dependency.id == "b")
+            .unwrap();
+        assert_eq!(b.applied_override, Some(dependency_override));
+        assert_eq!(b.required_by, vec!["a".to_string()]);
+
+        // overrides restricted to another range leave the requirement alone
+        root.overrides[0].version_range = Some(VersionReq::parse("^3.0.0").unwrap());
+        let resolution = Resolver::new(&registry).resolve(&root).unwrap();
+        assert_eq!(get_version(&resolution, "b"), Some(&Version::new(1, 0, 0)));
+    }
+}
@@ -0,0 +1,281 @@
+use std::{fs, io, path::Path};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use semver::Version;
+use thiserror::Error;
+
+use crate::{
+    integrity::sha256_bytes,
+    models::{artifact::ArtifactKind, dependency::SharedDependency},
+};
+
+#[derive(Debug, Error)]
+pub enum SignatureError {
+    #[error("invalid ed25519 key: {0}")]
+    InvalidKey(String),
+
+    #[error("invalid ed25519 signature: {0}")]
+    InvalidSignature(String),
+
+    #[error("signature does not match")]
+    Mismatch,
+
+    #[error("{kind:?} of {id} {version} is not signed")]
+    MissingSignature {
+        id: String,
+        version: Version,
+        kind: ArtifactKind,
+    },
+
+    #[error("{kind:?} of {id} {version} is not signed by the publisher key")]
+    ArtifactMismatch {
+        id: String,
+        version: Version,
+        kind: ArtifactKind,
+    },
+
+    #[error("publisher key of {id} changed from {pinned} to {found}")]
+    KeyChanged {
+        id: String,
+        pinned: String,
+        found: String,
+    },
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+fn decode<const N: usize>(hex_str: &str) -> Result<[u8; N], String> {
+    let bytes = hex::decode(hex_str.trim()).map_err(|e| e.to_string())?;
+    bytes
+        .try_into()
+        .map_err(|b: Vec<u8>| format!("expected {N} bytes but found {}", b.len()))
+}
+
+/// Parses a hex encoded ed25519 secret key
+pub fn parse_signing_key(secret_key: &str) -> Result<SigningKey, SignatureError> {
+    let bytes = decode::<32>(secret_key).map_err(SignatureError::InvalidKey)?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// Parses a hex encoded ed25519 public key, such as `PackageMetadata::publisher_key`
+pub fn parse_verifying_key(public_key: &str) -> Result<VerifyingKey, SignatureError> {
+    let bytes = decode::<32>(public_key).map_err(SignatureError::InvalidKey)?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| SignatureError::InvalidKey(e.to_string()))
+}
+
+/// The hex encoded public key to publish for a secret key
+pub fn get_public_key(signing_key: &SigningKey) -> String {
+    hex::encode(signing_key.verifying_key().as_bytes())
+}
+
+/// Signs data, returning the hex encoded detached signature
+pub fn sign(data: &[u8], signing_key: &SigningKey) -> String {
+    hex::encode(signing_key.sign(data).to_bytes())
+}
+
+/// Signs a file such as a .so or .qmod, returning the hex encoded detached signature
+pub fn sign_file(
+    path: impl AsRef<Path>,
+    signing_key: &SigningKey,
+) -> Result<String, SignatureError> {
+    Ok(sign(&fs::read(path)?, signing_key))
+}
+
+/// Verifies a hex encoded detached signature of data
+pub fn verify(data: &[u8], signature: &str, public_key: &str) -> Result<(), SignatureError> {
+    let key = parse_verifying_key(public_key)?;
+    let signature = decode::<64>(signature).map_err(SignatureError::InvalidSignature)?;
+
+    key.verify_strict(data, &Signature::from_bytes(&signature))
+        .map_err(|_| SignatureError::Mismatch)
+}
+
+/// Verifies a hex encoded detached signature of a file
+pub fn verify_file(
+    path: impl AsRef<Path>,
+    signature: &str,
+    public_key: &str,
+) -> Result<(), SignatureError> {
+    verify(&fs::read(path)?, signature, public_key)
+}
+
+/// The message signed for an artifact of a package.
+/// It binds the signature to the package, version and kind, so a signed artifact can't be passed off as another.
+pub fn get_artifact_message(
+    id: &str,
+    version: &Version,
+    kind: ArtifactKind,
+    sha256: &str,
+) -> Vec<u8> {
+    let kind = match kind {
+        ArtifactKind::So => "so",
+        ArtifactKind::DebugSo => "debugSo",
+        ArtifactKind::Static => "static",
+        ArtifactKind::Mod => "mod",
+        ArtifactKind::Source => "source",
+    };
+    let sha256 = sha256.to_ascii_lowercase();
+    format!("qpm-artifact-v1\nid={id}\nversion={version}\nkind={kind}\nsha256={sha256}\n")
+        .into_bytes()
+}
+
+/// Signs an artifact of a package, returning the hex encoded signature to publish in `signatures`
+pub fn sign_artifact(
+    id: &str,
+    version: &Version,
+    kind: ArtifactKind,
+    data: &[u8],
+    signing_key: &SigningKey,
+) -> String {
+    let message = get_artifact_message(id, version, kind, &sha256_bytes(data));
+    sign(&message, signing_key)
+}
+
+/// Checks that the publisher key of a package has not changed since it was pinned
+pub fn check_publisher_key(
+    id: &str,
+    pinned: Option<&str>,
+    found: Option<&str>,
+) -> Result<(), SignatureError> {
+    match (pinned, found) {
+        (Some(pinned), found) if !found.is_some_and(|f| f.eq_ignore_ascii_case(pinned)) => {
+            Err(SignatureError::KeyChanged {
+                id: id.to_string(),
+                pinned: pinned.to_string(),
+                found: found.unwrap_or("none").to_string(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Verifies a downloaded artifact of a dependency against its pinned publisher key, see [`sign_artifact`].
+/// Dependencies without a pinned key are not checked.
+pub fn verify_dependency_artifact(
+    dependency: &SharedDependency,
+    kind: ArtifactKind,
+    data: &[u8],
+) -> Result<(), SignatureError> {
+    let Some(publisher_key) = &dependency.publisher_key else {
+        return Ok(());
+    };
+
+    #[allow(deprecated)]
+    let metadata = &dependency.dependency.additional_data;
+    let signature = metadata.signatures.as_ref().and_then(|s| s.get(&kind));
+
+    let Some(signature) = signature else {
+        return Err(SignatureError::MissingSignature {
+            id: dependency.dependency.id.clone(),
+            version: dependency.version.clone(),
+            kind,
+        });
+    };
+
+    let id = &dependency.dependency.id;
+    let message = get_artifact_message(id, &dependency.version, kind, &sha256_bytes(data));
+    verify(&message, signature, publisher_key).map_err(|e| match e {
+        SignatureError::Mismatch => SignatureError::ArtifactMismatch {
+            id: dependency.dependency.id.clone(),
+            version: dependency.version.clone(),
+            kind,
+        },
+        e => e,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{dependency::Dependency, extra::AdditionalPackageMetadata};
+
+    const SECRET_KEY: &str = "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60";
+
+    #[allow(deprecated)]
+    fn dependency(id: &str, kind: ArtifactKind, signature: String, key: &str) -> SharedDependency {
+        SharedDependency {
+            dependency: Dependency {
+                id: id.to_string(),
+                version_range: semver::VersionReq::STAR,
+                additional_data: AdditionalPackageMetadata {
+                    signatures: Some([(kind, signature)].into()),
+                    ..Default::default()
+                },
+            },
+            version: Version::new(1, 0, 0),
+            checksums: Default::default(),
+            publisher_key: Some(key.to_string()),
+            source: None,
+            required_by: vec![],
+            declared_id: None,
+            applied_override: None,
+        }
+    }
+
+    #[test]
+    fn signs_and_verifies_data() {
+        let signing_key = parse_signing_key(SECRET_KEY).unwrap();
+        let public_key = get_public_key(&signing_key);
+
+        let signature = sign(b"data", &signing_key);
+        verify(b"data", &signature, &public_key).unwrap();
+        assert!(matches!(
+            verify(b"other", &signature, &public_key),
+            Err(SignatureError::Mismatch)
+        ));
+        assert!(matches!(
+            verify(b"data", "00", &public_key),
+            Err(SignatureError::InvalidSignature(_))
+        ));
+        assert!(matches!(
+            parse_verifying_key("not hex"),
+            Err(SignatureError::InvalidKey(_))
+        ));
+    }
+
+    #[test]
+    fn binds_artifact_signatures_to_package_version_and_kind() {
+        let signing_key = parse_signing_key(SECRET_KEY).unwrap();
+        let public_key = get_public_key(&signing_key);
+        let version = Version::new(1, 0, 0);
+        let signature = sign_artifact("a", &version, ArtifactKind::So, b"lib", &signing_key);
+
+        let signed = dependency("a", ArtifactKind::So, signature.clone(), &public_key);
+        verify_dependency_artifact(&signed, ArtifactKind::So, b"lib").unwrap();
+        assert!(matches!(
+            verify_dependency_artifact(&signed, ArtifactKind::So, b"other"),
+            Err(SignatureError::ArtifactMismatch { .. })
+        ));
+        assert!(matches!(
+            verify_dependency_artifact(&signed, ArtifactKind::DebugSo, b"lib"),
+            Err(SignatureError::MissingSignature { .. })
+        ));
+
+        // the signature of one artifact does not verify another package or kind with the same content
+        let other_id = dependency("b", ArtifactKind::So, signature.clone(), &public_key);
+        let other_kind = dependency("a", ArtifactKind::Static, signature, &public_key);
+        assert!(matches!(
+            verify_dependency_artifact(&other_id, ArtifactKind::So, b"lib"),
+            Err(SignatureError::ArtifactMismatch { .. })
+        ));
+        assert!(matches!(
+            verify_dependency_artifact(&other_kind, ArtifactKind::Static, b"lib"),
+            Err(SignatureError::ArtifactMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_changed_publisher_keys() {
+        check_publisher_key("a", None, Some("abcd")).unwrap();
+        check_publisher_key("a", Some("ABCD"), Some("abcd")).unwrap();
+        assert!(matches!(
+            check_publisher_key("a", Some("abcd"), Some("ef01")),
+            Err(SignatureError::KeyChanged { found, .. }) if found == "ef01"
+        ));
+        assert!(matches!(
+            check_publisher_key("a", Some("abcd"), None),
+            Err(SignatureError::KeyChanged { found, .. }) if found == "none"
+        ));
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Cursor},
+};
+
+use thiserror::Error;
+use zip::{result::ZipError, ZipArchive};
+
+use crate::{
+    credentials::redact_url,
+    extensions::package_config::PACKAGE_FILE_NAME,
+    fetch::{FetchError, Fetcher},
+    git::{
+        find_commit, get_archive_url, get_refs_url, parse_ref_advertisement, ArchiveTemplate,
+        GitUrlError,
+    },
+    models::{
+        package::PackageConfig,
+        source::{DependencySource, GitReference},
+    },
+};
+
+/// Urls in errors are redacted
+#[derive(Debug, Error)]
+pub enum SourceError {
+    #[error("only git repositories and urls can be loaded as a source")]
+    Unsupported,
+
+    #[error("{url} has no {reference:?}")]
+    ReferenceNotFound {
+        url: String,
+        reference: Option<GitReference>,
+    },
+
+    #[error("the archive {url} contains no {path}")]
+    MissingPackageConfig { url: String, path: String },
+
+    #[error(transparent)]
+    GitUrl(#[from] GitUrlError),
+
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+
+    #[error(transparent)]
+    Zip(#[from] ZipError),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// A package restored from a git repository or url rather than a registry
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadedSource {
+    /// The qpm.json of the package, used as the metadata of the dependency
+    pub config: PackageConfig,
+    /// The exact source the package was loaded from, git sources are pinned to a commit
+    pub source: DependencySource,
+}
+
+/// Loads the packages of dependencies that are restored from a git repository or url
+pub trait SourceLoader {
+    /// Loads the qpm.json of the package at a git or url source,
+    /// resolving the reference of a git source to the commit it points to
+    fn load(&self, source: &DependencySource) -> Result<LoadedSource, SourceError>;
+}
+
+impl<L: SourceLoader + ?Sized> SourceLoader for &L {
+    fn load(&self, source: &DependencySource) -> Result<LoadedSource, SourceError> {
+        (**self).load(source)
+    }
+}
+
+/// Loads sources from their zip archive.
+/// Git references are resolved through the smart HTTP protocol and the repository is downloaded
+/// from the archive url of its host at the resolved commit.
+#[derive(Debug)]
+pub struct ArchiveSourceLoader<F: Fetcher> {
+    pub fetcher: F,
+    /// Archive url templates by host, for self-hosted git servers
+    pub archive_templates: BTreeMap<String, ArchiveTemplate>,
+}
+
+impl<F: Fetcher> ArchiveSourceLoader<F> {
+    pub fn new(fetcher: F) -> Self {
+        Self {
+            fetcher,
+            archive_templates: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_archive_templates(
+        mut self,
+        archive_templates: BTreeMap<String, ArchiveTemplate>,
+    ) -> Self {
+        self.archive_templates = archive_templates;
+        self
+    }
+
+    fn get_commit(
+        &self,
+        url: &str,
+        reference: Option<&GitReference>,
+    ) -> Result<String, SourceError> {
+        if let Some(GitReference::Rev(rev)) = reference {
+            return Ok(rev.clone());
+        }
+
+        let refs = parse_ref_advertisement(&self.fetcher.fetch(&get_refs_url(url)?)?);
+        find_commit(&refs, reference).ok_or_else(|| SourceError::ReferenceNotFound {
+            url: redact_url(url).into_owned(),
+            reference: reference.cloned(),
+        })
+    }
+
+    /// Reads the qpm.json in the sub folder of an archive.
+    /// Archives of git hosts wrap the repository in a folder, which is skipped.
+    fn load_archive(
+        &self,
+        url: &str,
+        sub_folder: Option<&str>,
+    ) -> Result<PackageConfig, SourceError> {
+        let path = match sub_folder.map(|s| s.trim_matches('/')) {
+            Some(sub_folder) if !sub_folder.is_empty() => {
+                format!("{sub_folder}/{PACKAGE_FILE_NAME}")
+            }
+            _ => PACKAGE_FILE_NAME.to_string(),
+        };
+
+        let mut archive = ZipArchive::new(Cursor::new(self.fetcher.fetch(url)?))?;
+        let name = archive
+            .file_names()
+            .find(|name| *name == path)
+            .or_else(|| {
+                archive
+                    .file_names()
+                    .find(|name| name.split_once('/').is_some_and(|(_, rest)| rest == path))
+            })
+            .map(str::to_string)
+            .ok_or_else(|| SourceError::MissingPackageConfig {
+                url: redact_url(url).into_owned(),
+                path,
+            })?;
+
+        let config = serde_json::from_reader(archive.by_name(&name)?)?;
+        Ok(config)
+    }
+}
+
+impl<F: Fetcher> SourceLoader for ArchiveSourceLoader<F> {
+    fn load(&self, source: &DependencySource) -> Result<LoadedSource, SourceError> {
+        match source {
+            DependencySource::Git {
+                url,
+                reference,
+                sub_folder,
+            } => {
+                let commit = self.get_commit(url, reference.as_ref())?;
+                let archive_url = get_archive_url(
+                    url,
+                    &GitReference::Rev(commit.clone()),
+                    &self.archive_templates,
+                )?;
+                Ok(LoadedSource {
+                    config: self.load_archive(&archive_url, sub_folder.as_deref())?,
+                    source: source.clone().with_commit(&commit),
+                })
+            }
+            DependencySource::Url { url, sub_folder } => Ok(LoadedSource {
+                config: self.load_archive(url, sub_folder.as_deref())?,
+                source: source.clone(),
+            }),
+            DependencySource::Registry { .. } | DependencySource::Path { .. } => {
+                Err(SourceError::Unsupported)
+            }
+        }
+    }
+}
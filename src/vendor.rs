@@ -0,0 +1,409 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    credentials::redact_url,
+    download::{
+        get_debug_so_name, get_source_location, get_url_file_name, is_safe_file_name, is_url,
+        DownloadPlanError, SourceLocation,
+    },
+    extensions::{
+        additional_metadata::AdditionalPackageMetadataExtensions,
+        mirror_config::MirrorConfigExtensions, package_metadata::PackageMetadataExtensions,
+        shared_dependency::SharedDependencyExtensions,
+        shared_package_config::SharedPackageConfigExtensions,
+    },
+    fetch::{FetchError, Fetcher},
+    git::{get_archive_url, ArchiveTemplate, GitUrlError},
+    integrity::{digest_eq, sha256_bytes, IntegrityError},
+    models::{
+        artifact::ArtifactKind,
+        dependency::{SharedDependency, SharedPackageConfig},
+        mirror::MirrorConfig,
+        package::PackageConfig,
+    },
+};
+
+pub const VENDOR_MANIFEST_FILE_NAME: &str = "vendor.json";
+
+#[derive(Debug, Error)]
+pub enum VendorError {
+    #[error("the package config of {0} is required to vendor it")]
+    MissingPackage(String),
+
+    #[error("the git source of {0} has no branch, tag or commit to vendor")]
+    UnpinnedSource(String),
+
+    #[error("{id} contains the unsafe file name {name:?}, which could leave the vendor directory")]
+    UnsafeFileName { id: String, name: PathBuf },
+
+    #[error(transparent)]
+    DownloadPlan(#[from] DownloadPlanError),
+
+    #[error(transparent)]
+    GitUrl(#[from] GitUrlError),
+
+    #[error(transparent)]
+    Fetch(#[from] FetchError),
+
+    #[error(transparent)]
+    Integrity(#[from] IntegrityError),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+/// An artifact copied into the vendor directory
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct VendoredArtifact {
+    /// The redacted url the artifact was downloaded from, for display only
+    pub url: String,
+    pub id: String,
+    pub version: Version,
+    /// Extra files given as urls have no kind
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ArtifactKind>,
+    /// Relative to the vendor directory
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+/// {vendor}/vendor.json, mapping the url of every vendored artifact to its file.
+/// Artifacts are keyed by the SHA-256 digest of their full url, see [`get_vendor_key`],
+/// so urls that only differ in redacted secrets stay apart and secrets are not written to disk.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct VendorManifest {
+    pub artifacts: BTreeMap<String, VendoredArtifact>,
+}
+
+/// The key of an artifact in [`VendorManifest::artifacts`]
+pub fn get_vendor_key(url: &str) -> String {
+    sha256_bytes(url.as_bytes())
+}
+
+impl VendorManifest {
+    pub fn open(vendor_dir: &Path) -> Result<Self, VendorError> {
+        let file = File::open(vendor_dir.join(VENDOR_MANIFEST_FILE_NAME))?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+
+    pub fn save(&self, vendor_dir: &Path) -> Result<(), VendorError> {
+        fs::create_dir_all(vendor_dir)?;
+        let file = File::create(vendor_dir.join(VENDOR_MANIFEST_FILE_NAME))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+/// The url the source archive of a dependency is downloaded from, `None` for local packages
+pub fn get_source_archive_url(
+    id: &str,
+    source: &SourceLocation,
+    archive_templates: &BTreeMap<String, ArchiveTemplate>,
+) -> Result<Option<String>, VendorError> {
+    match source {
+        SourceLocation::Zip { url } => Ok(Some(url.clone())),
+        SourceLocation::Git {
+            url,
+            reference: Some(reference),
+        } => Ok(Some(get_archive_url(url, reference, archive_templates)?)),
+        SourceLocation::Git {
+            reference: None, ..
+        } => Err(VendorError::UnpinnedSource(id.to_string())),
+        SourceLocation::Local { .. } => Ok(None),
+    }
+}
+
+/// Copies every artifact `shared` references into `vendor_dir` and writes its manifest, so it can be
+/// restored without network access through [`VendorFetcher`].
+/// Artifacts are keyed by the urls in `shared`, so mirrors should not be applied to it beforehand.
+///
+/// This includes the source archive, all linked binaries and the extra files given as urls of every
+/// restored dependency, stored in `{id}/{version}/`. Binaries are verified against their recorded
+/// digests. Git sources are vendored as archives of their pinned reference, sources of local packages
+/// are not vendored. Ids and file names that are not a single path segment fail with
+/// [`VendorError::UnsafeFileName`], since they come from remote package configs.
+pub fn vendor(
+    shared: &SharedPackageConfig,
+    packages: &BTreeMap<String, PackageConfig>,
+    fetcher: &impl Fetcher,
+    vendor_dir: &Path,
+    archive_templates: &BTreeMap<String, ArchiveTemplate>,
+) -> Result<VendorManifest, VendorError> {
+    let mut manifest = VendorManifest::default();
+
+    for dependency in &shared.restored_dependencies {
+        let id = &dependency.dependency.id;
+        let package = packages
+            .get(id)
+            .ok_or_else(|| VendorError::MissingPackage(id.clone()))?;
+
+        let (source, _) = get_source_location(shared, dependency, package)?;
+        let mut urls = vec![];
+        if let Some(url) = get_source_archive_url(id, &source, archive_templates)? {
+            urls.push((ArtifactKind::Source, url));
+        }
+
+        #[allow(deprecated)]
+        let metadata = &dependency.dependency.additional_data;
+        for kind in [
+            ArtifactKind::So,
+            ArtifactKind::DebugSo,
+            ArtifactKind::Static,
+            ArtifactKind::Mod,
+        ] {
+            if let Some(url) = metadata.get_link(kind) {
+                urls.push((kind, url.clone()));
+            }
+        }
+
+        let version_dir =
+            PathBuf::from(check_file_name(id, id)?).join(dependency.version.to_string());
+        let mut files = vec![];
+        for (kind, url) in urls {
+            let name = get_vendored_file_name(dependency, kind);
+            files.push((
+                Some(kind),
+                url,
+                version_dir.join(check_file_name(id, name)?),
+            ));
+        }
+
+        let extra_files = shared
+            .get_dependency_modifier(dependency)
+            .and_then(|m| m.extra_files.as_ref());
+        for url in extra_files.into_iter().flatten().filter(|f| is_url(f)) {
+            let name = check_file_name(id, get_url_file_name(url))?;
+            files.push((None, url.clone(), version_dir.join("extra").join(name)));
+        }
+
+        for (kind, url, path) in files {
+            let key = get_vendor_key(&url);
+            if manifest.artifacts.contains_key(&key) {
+                continue;
+            }
+
+            let data = fetcher.fetch(&url)?;
+            let sha256 = match kind {
+                Some(kind) => dependency.verify_artifact(kind, &data)?,
+                None => sha256_bytes(&data),
+            };
+
+            let dest = vendor_dir.join(&path);
+            fs::create_dir_all(dest.parent().unwrap())?;
+            fs::write(dest, data)?;
+
+            manifest.artifacts.insert(
+                key,
+                VendoredArtifact {
+                    url: redact_url(&url).into_owned(),
+                    id: id.clone(),
+                    version: dependency.version.clone(),
+                    kind,
+                    path,
+                    sha256,
+                },
+            );
+        }
+    }
+
+    manifest.save(vendor_dir)?;
+    Ok(manifest)
+}
+
+/// `name` if it is a single path segment, see [`is_safe_file_name`]
+fn check_file_name<P: AsRef<Path>>(id: &str, name: P) -> Result<P, VendorError> {
+    match is_safe_file_name(name.as_ref()) {
+        true => Ok(name),
+        false => Err(VendorError::UnsafeFileName {
+            id: id.to_string(),
+            name: name.as_ref().to_path_buf(),
+        }),
+    }
+}
+
+fn get_vendored_file_name(dependency: &SharedDependency, kind: ArtifactKind) -> PathBuf {
+    match kind {
+        ArtifactKind::So => dependency.get_so_name2(),
+        ArtifactKind::DebugSo => get_debug_so_name(dependency),
+        ArtifactKind::Static => dependency.get_static_name2(),
+        ArtifactKind::Mod => format!("{}.qmod", dependency.dependency.id).into(),
+        ArtifactKind::Source => "source.zip".into(),
+    }
+}
+
+/// Serves artifacts only from a vendor directory, for restoring without network access.
+/// Urls missing from the manifest fail instead of being downloaded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VendorFetcher {
+    pub dir: PathBuf,
+    pub manifest: VendorManifest,
+    /// Maps mirrored urls back to the original urls the artifacts were vendored with
+    pub mirrors: MirrorConfig,
+}
+
+impl VendorFetcher {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, VendorError> {
+        let dir = dir.into();
+        let manifest = VendorManifest::open(&dir)?;
+        Ok(Self {
+            dir,
+            manifest,
+            mirrors: MirrorConfig::default(),
+        })
+    }
+
+    /// Serves the urls download plans rewrite to these mirrors from the artifacts of their original urls
+    pub fn with_mirrors(mut self, mirrors: MirrorConfig) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+}
+
+impl Fetcher for VendorFetcher {
+    fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        let original_urls = self.mirrors.get_original_urls(url);
+        let artifact = [url.to_string()]
+            .iter()
+            .chain(&original_urls)
+            .find_map(|url| self.manifest.artifacts.get(&get_vendor_key(url)));
+        let url = redact_url(url);
+        let artifact = artifact.ok_or_else(|| FetchError::NotVendored(url.to_string()))?;
+
+        let data = fs::read(self.dir.join(&artifact.path))?;
+        let actual = sha256_bytes(&data);
+        if !digest_eq(&artifact.sha256, &actual) {
+            return Err(FetchError::Corrupted {
+                url: url.to_string(),
+                expected: artifact.sha256.clone(),
+                actual,
+            });
+        }
+
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use semver::VersionReq;
+
+    use super::*;
+    use crate::models::{
+        dependency::Dependency, extra::AdditionalPackageMetadata, mirror::MirrorRule,
+        source::DependencySource,
+    };
+
+    /// Serves fixed bodies by url
+    struct MapFetcher(BTreeMap<&'static str, &'static [u8]>);
+
+    impl Fetcher for MapFetcher {
+        fn fetch(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+            self.0
+                .get(url)
+                .map(|data| data.to_vec())
+                .ok_or_else(|| FetchError::NotVendored(url.to_string()))
+        }
+    }
+
+    /// `dep` restored from a zip, with binaries whose urls only differ in a secret
+    fn get_shared() -> (SharedPackageConfig, BTreeMap<String, PackageConfig>) {
+        let metadata = AdditionalPackageMetadata {
+            so_link: Some("https://files.test/lib.so?token=a".to_string()),
+            mod_link: Some("https://files.test/lib.so?token=b".to_string()),
+            ..Default::default()
+        };
+        #[allow(deprecated)]
+        let restored = SharedDependency {
+            dependency: Dependency {
+                id: "dep".to_string(),
+                version_range: VersionReq::STAR,
+                additional_data: metadata,
+            },
+            version: Version::new(1, 0, 0),
+            checksums: BTreeMap::new(),
+            publisher_key: None,
+            source: Some(DependencySource::Url {
+                url: "https://files.test/dep.zip".to_string(),
+                sub_folder: None,
+            }),
+            required_by: vec![],
+            declared_id: None,
+            applied_override: None,
+        };
+
+        let mut package = PackageConfig::default();
+        package.info.id = "dep".to_string();
+        let shared = SharedPackageConfig {
+            config: PackageConfig::default(),
+            restored_dependencies: vec![restored],
+        };
+        (shared, BTreeMap::from([("dep".to_string(), package)]))
+    }
+
+    fn get_fetcher() -> MapFetcher {
+        MapFetcher(BTreeMap::from([
+            ("https://files.test/dep.zip", b"zip" as &[u8]),
+            ("https://files.test/lib.so?token=a", b"so"),
+            ("https://files.test/lib.so?token=b", b"mod"),
+        ]))
+    }
+
+    #[test]
+    fn vendors_urls_differing_in_secrets_separately() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let (shared, packages) = get_shared();
+
+        let manifest = vendor(&shared, &packages, &get_fetcher(), dir, &BTreeMap::new()).unwrap();
+        assert_eq!(manifest.artifacts.len(), 3);
+        let json = fs::read_to_string(dir.join(VENDOR_MANIFEST_FILE_NAME)).unwrap();
+        assert!(!json.contains("token=a"));
+        assert!(json.contains("https://files.test/lib.so?token=***"));
+
+        let vendored = VendorFetcher::open(dir).unwrap();
+        let fetch = |url| vendored.fetch(url).unwrap();
+        assert_eq!(fetch("https://files.test/lib.so?token=a"), b"so");
+        assert_eq!(fetch("https://files.test/lib.so?token=b"), b"mod");
+        assert!(matches!(
+            vendored.fetch("https://files.test/lib.so?token=c"),
+            Err(FetchError::NotVendored(url)) if url == "https://files.test/lib.so?token=***"
+        ));
+    }
+
+    #[test]
+    fn serves_mirrored_urls_from_original_urls() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let (shared, packages) = get_shared();
+        vendor(&shared, &packages, &get_fetcher(), dir, &BTreeMap::new()).unwrap();
+
+        let mirrors = MirrorConfig {
+            rules: vec![MirrorRule {
+                prefix: "https://files.test/".to_string(),
+                mirrors: vec!["https://mirror.test/".to_string()],
+                fallback_to_original: Some(false),
+            }],
+        };
+        let vendored = VendorFetcher::open(dir).unwrap();
+        assert!(vendored.fetch("https://mirror.test/dep.zip").is_err());
+
+        let vendored = vendored.with_mirrors(mirrors);
+        let fetch = |url| vendored.fetch(url).unwrap();
+        assert_eq!(fetch("https://mirror.test/dep.zip"), b"zip");
+        assert_eq!(fetch("https://files.test/dep.zip"), b"zip");
+    }
+}